@@ -0,0 +1,67 @@
+//! Detect the compiler features the generated trampolines rely on. Proc
+//! macros are built by the same compiler as the crate using them, so the
+//! version seen here is also the version the expansion is compiled with.
+
+use std::{env, process::Command};
+
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(hopter_unsafe_naked)");
+    println!("cargo:rustc-check-cfg=cfg(hopter_naked_asm)");
+    println!("cargo:rustc-check-cfg=cfg(hopter_global_asm)");
+    println!("cargo:rustc-check-cfg=cfg(hopter_unsafe_attr_extern)");
+    println!("cargo:rustc-check-cfg=cfg(hopter_diagnostic)");
+
+    // Assume a recent stable compiler if the version cannot be determined.
+    let version = rustc_version();
+    let minor = version
+        .as_deref()
+        .and_then(minor_version)
+        .unwrap_or(u32::MAX);
+    let nightly = version
+        .as_deref()
+        .is_some_and(|version| version.contains("-nightly") || version.contains("-dev"));
+
+    // Naked functions are stable as `#[unsafe(naked)]` with `naked_asm!`.
+    // Older stable compilers have no naked functions at all, and define the
+    // trampolines with `global_asm!` instead.
+    if minor >= 88 {
+        println!("cargo:rustc-cfg=hopter_unsafe_naked");
+    } else if !nightly {
+        println!("cargo:rustc-cfg=hopter_global_asm");
+    }
+
+    // Nightly naked functions take a `naked_asm!` body from Rust 1.84, and
+    // reject `asm!` from then on.
+    if minor >= 84 {
+        println!("cargo:rustc-cfg=hopter_naked_asm");
+    }
+
+    // `unsafe extern` blocks and `#[unsafe(..)]` attributes such as
+    // `#[unsafe(no_mangle)]`, both required by the 2024 edition.
+    if minor >= 82 {
+        println!("cargo:rustc-cfg=hopter_unsafe_attr_extern");
+    }
+
+    // `#[diagnostic::on_unimplemented]`, used for clearer type errors.
+    if minor >= 78 {
+        println!("cargo:rustc-cfg=hopter_diagnostic");
+    }
+}
+
+/// Return the version string of the compiler, e.g. `rustc 1.88.0 (...)`.
+fn rustc_version() -> Option<String> {
+    let rustc = env::var_os("RUSTC")?;
+    let output = Command::new(rustc).arg("--version").output().ok()?;
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Return the minor version in a version string, e.g. 88 for `rustc 1.88.0`.
+fn minor_version(version: &str) -> Option<u32> {
+    version
+        .split_whitespace()
+        .nth(1)?
+        .split('.')
+        .nth(1)?
+        .parse()
+        .ok()
+}
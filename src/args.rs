@@ -0,0 +1,241 @@
+//! Parsing of the attribute arguments shared by the macros.
+//!
+//! `syn::AttributeArgs` only accepts literals on the right hand side of
+//! `name = value`, while options such as `crate = my_bsp::hopter` take paths.
+//! Arguments are therefore parsed as either a bare path or `name = expr`.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use std::{fmt::Display, ops::RangeInclusive, str::FromStr};
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
+    parse_quote,
+    punctuated::Punctuated,
+    Error, Expr, ExprLit, ExprPath, Ident, Lit, Path, Result, Token,
+};
+
+/// A single attribute argument.
+pub(crate) enum AttrArg {
+    /// A bare path, e.g. `TIM7` or `unmask`.
+    Path(Path),
+    /// A named value, e.g. `priority = 3` or `crate = my_bsp::hopter`.
+    NameValue {
+        name: Ident,
+        eq_token: Token![=],
+        value: Box<Expr>,
+    },
+}
+
+impl Parse for AttrArg {
+    fn parse(input: ParseStream) -> Result<Self> {
+        // Option names may be keywords such as `crate`.
+        if input.peek(Ident::peek_any) && input.peek2(Token![=]) {
+            return Ok(AttrArg::NameValue {
+                name: input.call(Ident::parse_any)?,
+                eq_token: input.parse()?,
+                value: input.parse()?,
+            });
+        }
+
+        input.parse().map(AttrArg::Path)
+    }
+}
+
+impl ToTokens for AttrArg {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        match self {
+            AttrArg::Path(path) => path.to_tokens(tokens),
+            AttrArg::NameValue {
+                name,
+                eq_token,
+                value,
+            } => {
+                name.to_tokens(tokens);
+                eq_token.to_tokens(tokens);
+                value.to_tokens(tokens);
+            }
+        }
+    }
+}
+
+/// The comma separated arguments of an attribute.
+pub(crate) struct AttrArgs(pub Vec<AttrArg>);
+
+impl Parse for AttrArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let args = Punctuated::<AttrArg, Token![,]>::parse_terminated(input)?;
+        Ok(AttrArgs(args.into_iter().collect()))
+    }
+}
+
+/// Interpret the value of an option as a plain path.
+pub(crate) fn expr_to_path(value: &Expr) -> Result<Path> {
+    match value {
+        Expr::Path(ExprPath {
+            attrs,
+            qself: None,
+            path,
+        }) if attrs.is_empty() => Ok(path.clone()),
+        _ => Err(Error::new_spanned(value, "Expected a path.")),
+    }
+}
+
+/// Parse an integer option, described by `what` in errors.
+pub(crate) fn parse_int<T>(value: &Expr, what: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) if int.suffix().is_empty() => int.base10_parse(),
+        _ => Err(Error::new_spanned(
+            value,
+            format!("{} must be an integer.", what),
+        )),
+    }
+}
+
+/// Parse a size in bytes, which may be given in KiB or MiB with a `K` or `M`
+/// suffix, e.g. `64K`. The size must lie within `range`.
+pub(crate) fn parse_size(value: &Expr, what: &str, range: RangeInclusive<usize>) -> Result<usize> {
+    let (int, unit) = match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => match int.suffix() {
+            "" => (int, 1),
+            "K" => (int, 1 << 10),
+            "M" => (int, 1 << 20),
+            _ => {
+                return Err(Error::new_spanned(
+                    int,
+                    format!("{} only accepts the `K` and `M` suffixes.", what),
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                value,
+                format!("{} must be an integer, e.g. `8192` or `8K`.", what),
+            ))
+        }
+    };
+
+    let size = int
+        .base10_parse::<usize>()?
+        .checked_mul(unit)
+        .filter(|size| range.contains(size))
+        .ok_or_else(|| {
+            Error::new_spanned(
+                int,
+                format!(
+                    "{} must be in the range {}..={} bytes.",
+                    what,
+                    range.start(),
+                    range.end()
+                ),
+            )
+        })?;
+
+    if size % 8 != 0 {
+        return Err(Error::new_spanned(
+            int,
+            format!("{} must be a multiple of 8 bytes.", what),
+        ));
+    }
+
+    Ok(size)
+}
+
+/// Paths through which the generated code refers to the runtime crates.
+pub(crate) struct CratePaths {
+    /// The Hopter crate.
+    pub hopter: Path,
+    /// The `cortex_m` crate.
+    pub cortex_m: TokenStream2,
+    /// The `cortex_m_rt` crate.
+    pub cortex_m_rt: TokenStream2,
+    /// The `alloc` crate.
+    pub alloc: TokenStream2,
+}
+
+impl CratePaths {
+    /// Without `crate = path`, the crates are expected to be direct
+    /// dependencies of the user crate. With it, the crate at `path` replaces
+    /// Hopter and must re-export `cortex_m`, `cortex_m_rt` and `alloc` at its
+    /// root, so that wrapper crates work without depending on them directly.
+    pub fn new(krate: Option<&Path>) -> Self {
+        match krate {
+            Some(krate) => CratePaths {
+                hopter: krate.clone(),
+                cortex_m: quote! { #krate::cortex_m },
+                cortex_m_rt: quote! { #krate::cortex_m_rt },
+                alloc: quote! { #krate::alloc },
+            },
+            None => CratePaths {
+                hopter: parse_quote! { ::hopter },
+                cortex_m: quote! { ::cortex_m },
+                cortex_m_rt: quote! { ::cortex_m_rt },
+                alloc: quote! { ::alloc },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(value: &str) -> Result<usize> {
+        let value: Expr = syn::parse_str(value).unwrap();
+        parse_size(&value, "Stack size", 8..=(1 << 20))
+    }
+
+    #[test]
+    fn parse_size_accepts_suffixes() {
+        assert_eq!(size("4096").unwrap(), 4096);
+        assert_eq!(size("64K").unwrap(), 64 << 10);
+        assert_eq!(size("1M").unwrap(), 1 << 20);
+    }
+
+    #[test]
+    fn parse_size_rejects_other_suffixes() {
+        let error = size("64k").unwrap_err().to_string();
+        assert_eq!(error, "Stack size only accepts the `K` and `M` suffixes.");
+        assert!(size("64usize").is_err());
+    }
+
+    #[test]
+    fn parse_size_rejects_non_integers() {
+        let error = size("SIZE").unwrap_err().to_string();
+        assert_eq!(error, "Stack size must be an integer, e.g. `8192` or `8K`.");
+    }
+
+    #[test]
+    fn parse_size_accepts_range_ends() {
+        assert_eq!(size("8").unwrap(), 8);
+        assert_eq!(size("1024K").unwrap(), 1 << 20);
+    }
+
+    #[test]
+    fn parse_size_rejects_out_of_range() {
+        let error = size("0").unwrap_err().to_string();
+        assert_eq!(error, "Stack size must be in the range 8..=1048576 bytes.");
+        assert!(size("1048584").is_err());
+        assert!(size("2M").is_err());
+    }
+
+    #[test]
+    fn parse_size_rejects_overflow() {
+        let error = size(&format!("{}M", usize::MAX)).unwrap_err().to_string();
+        assert_eq!(error, "Stack size must be in the range 8..=1048576 bytes.");
+    }
+
+    #[test]
+    fn parse_size_requires_multiple_of_8() {
+        let error = size("1020").unwrap_err().to_string();
+        assert_eq!(error, "Stack size must be a multiple of 8 bytes.");
+    }
+}
@@ -0,0 +1,277 @@
+//! Implementation of the [`#[exception]`](crate::exception) attribute macro.
+
+use crate::{
+    args::{self, AttrArg, CratePaths},
+    error_with_item, generate_type_assertion, trampoline, Errors, ForwardedAttrs,
+};
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{Abi, Error, FnArg, ItemFn, Path, Result, ReturnType, Signature, Type};
+
+/// A core Cortex-M exception that can be bound with `#[exception]`.
+struct Exception {
+    /// The name used in the attribute.
+    name: &'static str,
+    /// The symbol the vector table refers to.
+    symbol: &'static str,
+}
+
+/// List of the core exceptions that user code may handle.
+const SUPPORTED_EXCEPTIONS: [Exception; 6] = [
+    Exception {
+        name: "NMI",
+        symbol: "NonMaskableInt",
+    },
+    Exception {
+        name: "HardFault",
+        symbol: "HardFault",
+    },
+    Exception {
+        name: "MemManage",
+        symbol: "MemoryManagement",
+    },
+    Exception {
+        name: "BusFault",
+        symbol: "BusFault",
+    },
+    Exception {
+        name: "UsageFault",
+        symbol: "UsageFault",
+    },
+    Exception {
+        name: "DebugMonitor",
+        symbol: "DebugMonitor",
+    },
+];
+
+/// List of the core exceptions owned by Hopter's scheduler.
+const RESERVED_EXCEPTIONS: [&str; 3] = ["SVCall", "PendSV", "SysTick"];
+
+/// The parsed arguments of the exception attribute.
+struct ExceptionArgs {
+    exception: &'static Exception,
+    crates: CratePaths,
+}
+
+macro_rules! exception_macro_arg_error {
+    () => {
+        "Exception's argument must be one of `NMI`, `HardFault`, `MemManage`, \
+        `BusFault`, `UsageFault` or `DebugMonitor`."
+    };
+}
+
+pub(crate) fn expand(attr_args: &[AttrArg], exception_func: ItemFn) -> TokenStream {
+    let mut errors = Errors::default();
+
+    let args = match parse_exception_args(attr_args) {
+        Ok(args) => Some(args),
+        Err(error) => {
+            errors.combine(error);
+            None
+        }
+    };
+
+    // The signature can only be checked once the exception is known.
+    if let Some(args) = &args {
+        if let Err(error) = check_exception_function_signature(&exception_func.sig, args.exception)
+        {
+            errors.combine(error);
+        }
+    }
+
+    if let Err(error) = errors.finish() {
+        return error_with_item(error, &exception_func);
+    }
+
+    let ExceptionArgs { exception, crates } = args.unwrap();
+
+    // Store the exception function's name.
+    let func_name = &exception_func.sig.ident;
+    let span = func_name.span();
+
+    let forwarded = ForwardedAttrs::new(&exception_func);
+
+    // The argument was checked to be a reference, which must point to the
+    // exception frame.
+    let cortex_m_rt = &crates.cortex_m_rt;
+    let frame_assertion = match exception_func.sig.inputs.first() {
+        Some(FnArg::Typed(arg)) => match &*arg.ty {
+            Type::Reference(reference) => Some(generate_type_assertion(
+                &reference.elem,
+                "HardFault handler's exception frame",
+                &quote!(#cortex_m_rt::ExceptionFrame),
+                &forwarded.cfg,
+            )),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    let symbol = exception.symbol;
+    let entry_name = format_ident!("__{}_entry", exception.name.to_lowercase(), span = span);
+
+    // `HardFault` receives the exception frame stacked on the stack that was
+    // active when the fault occurred, as indicated by bit 2 of `EXC_RETURN`.
+    let load_frame: &[&str] = if exception.name == "HardFault" {
+        &["tst lr, #4", "ite eq", "mrseq r0, msp", "mrsne r0, psp"]
+    } else {
+        &[]
+    };
+
+    // Generate the trampoline function.
+    let instructions = [load_frame, &["b {handler_func}"]].concat();
+    let trampoline = trampoline::generate_entry(
+        symbol,
+        &entry_name,
+        &instructions,
+        quote_spanned! {span=> handler_func = sym #func_name, },
+        &forwarded,
+    );
+
+    // Output the trampoline followed by the original exception function.
+    quote! {
+        #frame_assertion
+        #trampoline
+        #exception_func
+    }
+    .into()
+}
+
+/// The exception attribute should contain one and only one exception name,
+/// which is a supported core exception, and may contain the `crate = path`
+/// option.
+fn parse_exception_args(attr_args: &[AttrArg]) -> Result<ExceptionArgs> {
+    let mut errors = Errors::default();
+
+    let mut exception = None;
+    let mut exception_seen = false;
+    let mut krate: Option<Path> = None;
+
+    for arg in attr_args {
+        match arg {
+            AttrArg::NameValue { name, value, .. } if name == "crate" => {
+                if krate.is_some() {
+                    errors.push(name, "Duplicated `crate` option.");
+                }
+                match args::expr_to_path(value) {
+                    Ok(path) => krate = Some(path),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            // Check that there is only one exception.
+            AttrArg::Path(ss) if exception_seen => errors.push(
+                ss,
+                "Exception handler must be bound to exactly one exception.",
+            ),
+            AttrArg::Path(ss) => {
+                exception_seen = true;
+
+                let arg = quote! { #ss }.to_string();
+                exception = SUPPORTED_EXCEPTIONS.iter().find(|e| e.name == arg);
+
+                if exception.is_none() {
+                    if RESERVED_EXCEPTIONS.contains(&arg.as_str()) {
+                        errors.push(
+                            ss,
+                            format!(
+                                "`{}` is used by Hopter's scheduler and cannot be handled.",
+                                arg
+                            ),
+                        );
+                    } else {
+                        errors.push(ss, exception_macro_arg_error!());
+                    }
+                }
+            }
+            _ => errors.push(arg, exception_macro_arg_error!()),
+        }
+    }
+
+    // Check that there is at least one exception.
+    if !exception_seen {
+        errors.combine(Error::new(Span::call_site(), exception_macro_arg_error!()));
+    }
+
+    errors.finish().map(|_| ExceptionArgs {
+        exception: exception.unwrap(),
+        crates: CratePaths::new(krate.as_ref()),
+    })
+}
+
+/// An exception function should satisfy the following signature
+/// requirements:
+/// - Has `extern "C"` ABI.
+/// - Is not `async`.
+/// - Is not variadic.
+///
+/// For `HardFault`, it should additionally:
+/// - Have one argument, a reference to the exception frame.
+/// - Return `!`.
+///
+/// For other exceptions, it should additionally:
+/// - Have no argument.
+/// - Return `()`.
+fn check_exception_function_signature(sig: &Signature, exception: &Exception) -> Result<()> {
+    let mut errors = Errors::default();
+
+    if exception.name == "HardFault" {
+        let frame_error = "HardFault handler must receive one argument of type \
+            `&cortex_m_rt::ExceptionFrame`.";
+
+        match sig.inputs.first() {
+            Some(FnArg::Typed(arg)) if matches!(&*arg.ty, Type::Reference(_)) => {}
+            Some(arg) => errors.push(arg, frame_error),
+            None => errors.push(&sig.ident, frame_error),
+        }
+
+        for extra in sig.inputs.iter().skip(1) {
+            errors.push(extra, frame_error);
+        }
+
+        match &sig.output {
+            ReturnType::Type(_, b) if matches!(&**b, Type::Never(_)) => {}
+            ReturnType::Type(_, b) => errors.push(b, "HardFault handler must return `!`."),
+            ReturnType::Default => errors.push(&sig.ident, "HardFault handler must return `!`."),
+        }
+    } else {
+        for input in sig.inputs.iter() {
+            errors.push(input, "Exception handler should not have any parameter.");
+        }
+
+        match &sig.output {
+            // No return type specification.
+            ReturnType::Default => {}
+            // Specified return type as `-> ()`.
+            ReturnType::Type(_, b) => match &**b {
+                Type::Tuple(t) if t.elems.is_empty() => {}
+                _ => errors.push(b, "Exception handler's return type must be ()."),
+            },
+        }
+    }
+
+    match sig.abi.as_ref() {
+        None => errors.push(sig.fn_token, "Exception handler must be `extern \"C\"`."),
+        Some(Abi { name: None, .. }) => errors.push(
+            sig.abi.as_ref(),
+            "Exception handler must be `extern \"C\"`.",
+        ),
+        Some(Abi {
+            name: Some(name), ..
+        }) => {
+            if name.value() != "C" {
+                errors.push(name, "Exception handler must be `extern \"C\"`.");
+            }
+        }
+    }
+
+    if let Some(asyncness) = &sig.asyncness {
+        errors.push(asyncness, "Exception handler cannot be `async`.");
+    }
+
+    if let Some(variadic) = &sig.variadic {
+        errors.push(variadic, "Exception handler cannot be variadic.");
+    }
+
+    errors.finish()
+}
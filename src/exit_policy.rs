@@ -0,0 +1,81 @@
+//! Handling of errors returned by the main function, configured with the
+//! `report` and `on_error` options of [`#[main]`](crate::main).
+//!
+//! The trampoline matches on the returned `Result`. An error is first
+//! reported through the hook, and the exit policy is then applied.
+
+use crate::args::{self, CratePaths};
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::quote_spanned;
+use syn::{Error, Expr, Path, Result};
+
+/// What to do after the main function returned an error.
+pub(crate) enum ExitPolicy {
+    /// Mask all interrupts and sleep forever, stopping the whole system.
+    Halt,
+    /// Reset the MCU.
+    Reset,
+    /// Panic, so that the kernel recovers by restarting the main task.
+    Restart,
+}
+
+/// Parse `halt`, `reset` or `restart`.
+pub(crate) fn parse_exit_policy(value: &Expr) -> Result<ExitPolicy> {
+    let path = args::expr_to_path(value)?;
+
+    match path.get_ident().map(|name| name.to_string()).as_deref() {
+        Some("halt") => Ok(ExitPolicy::Halt),
+        Some("reset") => Ok(ExitPolicy::Reset),
+        Some("restart") => Ok(ExitPolicy::Restart),
+        _ => Err(Error::new_spanned(
+            path,
+            "Exit policy must be one of `halt`, `reset` or `restart`.",
+        )),
+    }
+}
+
+/// Wrap `call`, which calls the main function returning a `Result`, so that
+/// an error is passed to the `report` hook, or printed through semihosting
+/// without a hook, before the `policy` is applied.
+pub(crate) fn handle_error(
+    call: TokenStream2,
+    policy: &ExitPolicy,
+    report: Option<&Path>,
+    func_name: &Ident,
+    crates: &CratePaths,
+) -> TokenStream2 {
+    let span = func_name.span();
+    let CratePaths {
+        hopter, cortex_m, ..
+    } = crates;
+
+    let report = match report {
+        Some(report) => quote_spanned! {span=> #report(&error); },
+        None => quote_spanned! {span=>
+            #hopter::debug::semihosting::dbg_println!("main returned an error: {:?}", error);
+        },
+    };
+
+    let on_error = match policy {
+        ExitPolicy::Halt => quote_spanned! {span=>
+            #cortex_m::interrupt::disable();
+            loop {
+                #cortex_m::asm::wfi();
+            }
+        },
+        ExitPolicy::Reset => quote_spanned! {span=>
+            #cortex_m::peripheral::SCB::sys_reset();
+        },
+        ExitPolicy::Restart => quote_spanned! {span=>
+            ::core::panic!("main returned an error");
+        },
+    };
+
+    quote_spanned! {span=>
+        let result: ::core::result::Result<(), _> = { #call };
+        if let ::core::result::Result::Err(error) = result {
+            #report
+            #on_error
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Software demultiplexing of the shared `EXTI9_5` and `EXTI15_10` vectors.
+//!
+//! Each handler bound to a virtual IRQ such as `EXTI7` exports a line
+//! function under the symbol `__hopter_exti_line7`, and a marker under the
+//! symbol `__hopter_exti_line7_bound`. Binding the same line twice thus fails
+//! with a duplicated marker symbol. A handler bound to a shared vector itself
+//! exports the markers of all its lines, as its vector replaces their
+//! dispatcher. The line function itself cannot serve
+//! for this, because the weak reference from a dispatcher in the same object
+//! file makes its definition weak as well.
+//!
+//! Every expansion binding a line also emits the dispatcher of the shared
+//! vector in assembly. The dispatcher is defined as a weak symbol and guarded
+//! by `.ifndef`, so that the copies emitted by several expansions collapse
+//! into one, both within an object file and across object files. It reads
+//! the pending EXTI lines, clears each pending bit, and calls the line
+//! functions that are linked in. Line functions are referred to weakly, so
+//! unbound lines resolve to address zero and are skipped.
+
+use crate::{irqs::ExtiLine, trampoline, ForwardedAttrs};
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, quote_spanned};
+use std::ops::RangeInclusive;
+use syn::{Abi, Path};
+
+/// Base address of the EXTI registers, identical on all STM32F4 and STM32F7.
+const EXTI_BASE: u32 = 0x4001_3c00;
+
+/// Offset of the interrupt mask register.
+const EXTI_IMR_OFFSET: u32 = 0x00;
+
+/// Offset of the pending register. Pending bits are cleared by writing 1.
+const EXTI_PR_OFFSET: u32 = 0x14;
+
+/// Generate the line function for `line`, whose `body` calls the handler
+/// function, together with the dispatcher of the shared vector serving the
+/// line. The line function has the same `abi` as the handler function.
+pub(crate) fn generate_line_binding(
+    func_name: &Ident,
+    abi: &Option<Abi>,
+    body: TokenStream2,
+    line: &ExtiLine,
+    hopter: &Path,
+    forwarded: &ForwardedAttrs,
+) -> TokenStream2 {
+    let span = func_name.span();
+    let ForwardedAttrs {
+        cfg: cfg_attrs,
+        func: func_attrs,
+        ..
+    } = forwarded;
+
+    let line_symbol = format!("__hopter_exti_line{}", line.line);
+    let line_func = format_ident!("__exti{}_line", line.line, span = span);
+
+    let dispatcher = dispatcher_asm(line);
+
+    let line_export =
+        trampoline::unsafe_attr(span, quote_spanned! {span=> export_name = #line_symbol });
+    let bound_marker = bound_marker(line.line, span, cfg_attrs);
+
+    quote_spanned! {span=>
+        #func_attrs
+        #line_export
+        #abi fn #line_func() {
+            #body
+        }
+
+        #bound_marker
+
+        #cfg_attrs
+        ::core::arch::global_asm!(
+            #dispatcher,
+            fast_irq_entry = sym #hopter::interrupt::default::fast_irq_entry,
+        );
+    }
+}
+
+/// Generate the bound markers of all lines served by `vector` if it is a
+/// shared vector, so that binding any of them as well fails when linking.
+pub(crate) fn claim_shared_vector(
+    vector: &str,
+    span: Span,
+    cfg_attrs: &TokenStream2,
+) -> TokenStream2 {
+    vector_lines(vector)
+        .into_iter()
+        .flatten()
+        .map(|line| bound_marker(line, span, cfg_attrs))
+        .collect()
+}
+
+/// The lines served by `vector`, if it is a shared vector.
+fn vector_lines(vector: &str) -> Option<RangeInclusive<u8>> {
+    match vector {
+        "EXTI9_5" => Some(5..=9),
+        "EXTI15_10" => Some(10..=15),
+        _ => None,
+    }
+}
+
+/// Generate the marker exported as `__hopter_exti_lineN_bound` for `line`.
+fn bound_marker(line: u8, span: Span, cfg_attrs: &TokenStream2) -> TokenStream2 {
+    let bound_symbol = format!("__hopter_exti_line{}_bound", line);
+    let bound_static = format_ident!("__EXTI{}_BOUND", line, span = span);
+    let bound_export =
+        trampoline::unsafe_attr(span, quote_spanned! {span=> export_name = #bound_symbol });
+
+    quote_spanned! {span=>
+        #cfg_attrs
+        #[used]
+        #bound_export
+        static #bound_static: u8 = 0;
+    }
+}
+
+/// Generate the statement masking `line` in the EXTI peripheral, leaving
+/// the other lines of the shared vector enabled.
+pub(crate) fn mask_line(line: &ExtiLine) -> TokenStream2 {
+    let imr = EXTI_BASE + EXTI_IMR_OFFSET;
+    let mask = 1u32 << line.line;
+
+    quote! {
+        unsafe {
+            let imr = #imr as *mut u32;
+            imr.write_volatile(imr.read_volatile() & !#mask);
+        }
+    }
+}
+
+/// Generate the assembly defining the shared vector of `line` and its
+/// dispatcher. The vector enters the kernel through `fast_irq_entry` like
+/// any other handler, which then calls the dispatcher. The dispatcher
+/// carries unwind annotations, so that a panic in a `C-unwind` handler
+/// unwinds through it.
+fn dispatcher_asm(line: &ExtiLine) -> String {
+    let vector = &line.vector.name;
+    let dispatch = format!("__hopter_{}_dispatch", vector.to_lowercase());
+
+    let lines = vector_lines(vector).expect("EXTI lines are served by a shared vector");
+
+    let mut asm = String::new();
+
+    asm.push_str(&format!(
+        "\
+        .ifndef {dispatch}\n\
+        .syntax unified\n\
+        .pushsection .text.{dispatch},\"ax\",%progbits\n\
+        .weak {dispatch}\n\
+        .type {dispatch},%function\n\
+        .thumb_func\n\
+        {dispatch}:\n\
+        .fnstart\n\
+        .save {{{{r4, r5, r6, lr}}}}\n\
+        push {{{{r4, r5, r6, lr}}}}\n\
+        ldr r4, ={base:#x}\n\
+        ldr r5, [r4, #{pr:#x}]\n\
+        ldr r6, [r4, #{imr:#x}]\n\
+        ands r5, r5, r6\n",
+        dispatch = dispatch,
+        base = EXTI_BASE,
+        pr = EXTI_PR_OFFSET,
+        imr = EXTI_IMR_OFFSET,
+    ));
+
+    // Numeric labels are offset by 20, as labels made of only `0` and `1`
+    // digits are ambiguous with binary literals.
+    for n in lines.clone() {
+        asm.push_str(&format!(
+            "\
+            tst r5, #{mask:#x}\n\
+            beq {label}f\n\
+            mov r0, #{mask:#x}\n\
+            str r0, [r4, #{pr:#x}]\n\
+            ldr r0, =__hopter_exti_line{n}\n\
+            cbz r0, {label}f\n\
+            blx r0\n\
+            {label}:\n",
+            mask = 1u32 << n,
+            label = n + 20,
+            pr = EXTI_PR_OFFSET,
+            n = n,
+        ));
+    }
+
+    asm.push_str(&format!(
+        "\
+        pop {{{{r4, r5, r6, pc}}}}\n\
+        .ltorg\n\
+        .fnend\n\
+        .size {dispatch}, . - {dispatch}\n\
+        .popsection\n",
+        dispatch = dispatch,
+    ));
+
+    for n in lines {
+        asm.push_str(&format!(".weak __hopter_exti_line{}\n", n));
+    }
+
+    asm.push_str(&format!(
+        "\
+        .pushsection .text.{vector},\"ax\",%progbits\n\
+        .weak {vector}\n\
+        .type {vector},%function\n\
+        .thumb_func\n\
+        {vector}:\n\
+        ldr r0, ={dispatch}\n\
+        b {{fast_irq_entry}}\n\
+        .ltorg\n\
+        .size {vector}, . - {vector}\n\
+        .popsection\n\
+        .endif\n",
+        vector = vector,
+        dispatch = dispatch,
+    ));
+
+    asm
+}
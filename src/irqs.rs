@@ -0,0 +1,897 @@
+//! Per-chip catalogs of the IRQ names accepted by [`#[handler]`](crate::handler).
+//!
+//! The catalog is selected with one of the mutually exclusive chip features
+//! of this crate. Without any chip feature, the generic STM32F4 catalog that
+//! earlier versions of this crate shipped with is used. With the `svd`
+//! feature, the catalog is instead read from a CMSIS-SVD file, see
+//! [`crate::svd`].
+
+use std::{borrow::Cow, path::PathBuf};
+
+/// A device IRQ and its NVIC number.
+#[derive(Clone)]
+pub(crate) struct Irq {
+    pub name: Cow<'static, str>,
+    pub number: u16,
+}
+
+impl Irq {
+    const fn new(name: &'static str, number: u16) -> Self {
+        Self {
+            name: Cow::Borrowed(name),
+            number,
+        }
+    }
+}
+
+/// A line of a shared EXTI vector, bound as a virtual IRQ such as `EXTI7`.
+pub(crate) struct ExtiLine {
+    /// The EXTI line number.
+    pub line: u8,
+    /// The shared vector serving the line, e.g. `EXTI9_5`.
+    pub vector: Irq,
+}
+
+/// The IRQ catalog of one chip family.
+#[derive(Clone)]
+pub(crate) struct Chip {
+    /// Name of the chip family, as spelled in its cargo feature.
+    pub name: Cow<'static, str>,
+    /// The device IRQs, in vector table order.
+    pub irqs: Cow<'static, [Irq]>,
+    /// Number of priority bits implemented by the NVIC.
+    pub nvic_prio_bits: u8,
+    /// The SVD file the catalog was read from, if not built into this crate.
+    pub svd_path: Option<PathBuf>,
+    /// The configuration file naming the SVD file, if it was used.
+    pub config_path: Option<PathBuf>,
+}
+
+impl Chip {
+    /// Return the IRQ named `name`, if the chip has one.
+    pub fn find_irq(&self, name: &str) -> Option<&Irq> {
+        self.irqs.iter().find(|irq| irq.name == name)
+    }
+
+    /// Return the EXTI line named `name`, e.g. `EXTI7`, if the line does not
+    /// have a vector of its own but shares one that the chip has.
+    pub fn find_exti_line(&self, name: &str) -> Option<ExtiLine> {
+        let line: u8 = name.strip_prefix("EXTI")?.parse().ok()?;
+
+        // Reject spellings such as `EXTI07`.
+        if format!("EXTI{}", line) != name {
+            return None;
+        }
+
+        let vector = match line {
+            5..=9 => "EXTI9_5",
+            10..=15 => "EXTI15_10",
+            _ => return None,
+        };
+
+        self.find_irq(vector).map(|vector| ExtiLine {
+            line,
+            vector: vector.clone(),
+        })
+    }
+}
+
+/// Return the catalog of the selected chip. With the `svd` feature, the
+/// catalog is read from the configured SVD file, which may fail.
+pub(crate) fn selected_chip() -> Result<Chip, String> {
+    #[cfg(feature = "svd")]
+    return crate::svd::load_chip();
+
+    #[cfg(not(feature = "svd"))]
+    Ok(SELECTED_CHIP)
+}
+
+/// Return whether the files the catalog of the selected chip was read from
+/// still have to be tracked, which the first expansion in a crate does.
+/// Built-in catalogs are not read from any file.
+pub(crate) fn take_svd_tracking() -> bool {
+    #[cfg(feature = "svd")]
+    return crate::svd::take_tracking();
+
+    #[cfg(not(feature = "svd"))]
+    false
+}
+
+/// Number of chip features that are enabled.
+const SELECTED_CHIPS: usize = cfg!(feature = "stm32f401") as usize
+    + cfg!(feature = "stm32f405") as usize
+    + cfg!(feature = "stm32f407") as usize
+    + cfg!(feature = "stm32f411") as usize
+    + cfg!(feature = "stm32f429") as usize
+    + cfg!(feature = "stm32f446") as usize
+    + cfg!(feature = "stm32f7xx") as usize
+    + cfg!(feature = "svd") as usize;
+
+// Always true without chip features, which clippy would otherwise flag.
+#[allow(clippy::absurd_extreme_comparisons)]
+const _: () = assert!(
+    SELECTED_CHIPS <= 1,
+    "At most one chip feature or the `svd` feature of `hopter_proc_macro` can be enabled."
+);
+
+/// The catalog of the chip selected by the enabled chip feature.
+#[cfg_attr(feature = "svd", allow(dead_code))]
+const SELECTED_CHIP: Chip = if cfg!(feature = "stm32f401") {
+    STM32F401
+} else if cfg!(feature = "stm32f405") {
+    STM32F405
+} else if cfg!(feature = "stm32f407") {
+    STM32F407
+} else if cfg!(feature = "stm32f411") {
+    STM32F411
+} else if cfg!(feature = "stm32f429") {
+    STM32F429
+} else if cfg!(feature = "stm32f446") {
+    STM32F446
+} else if cfg!(feature = "stm32f7xx") {
+    STM32F7XX
+} else {
+    STM32F4
+};
+
+/// Generic STM32F4 catalog, used when no chip feature is enabled.
+const STM32F4: Chip = Chip {
+    name: Cow::Borrowed("stm32f4"),
+    irqs: Cow::Borrowed(&[
+        Irq::new("WWDG", 0),
+        Irq::new("PVD", 1),
+        Irq::new("TAMP_STAMP", 2),
+        Irq::new("RTC_WKUP", 3),
+        Irq::new("RCC", 5),
+        Irq::new("EXTI0", 6),
+        Irq::new("EXTI1", 7),
+        Irq::new("EXTI2", 8),
+        Irq::new("EXTI3", 9),
+        Irq::new("EXTI4", 10),
+        Irq::new("DMA1_STREAM0", 11),
+        Irq::new("DMA1_STREAM1", 12),
+        Irq::new("DMA1_STREAM2", 13),
+        Irq::new("DMA1_STREAM3", 14),
+        Irq::new("DMA1_STREAM4", 15),
+        Irq::new("DMA1_STREAM5", 16),
+        Irq::new("DMA1_STREAM6", 17),
+        Irq::new("ADC", 18),
+        Irq::new("CAN1_TX", 19),
+        Irq::new("CAN1_RX0", 20),
+        Irq::new("CAN1_RX1", 21),
+        Irq::new("CAN1_SCE", 22),
+        Irq::new("EXTI9_5", 23),
+        Irq::new("TIM1_BRK_TIM9", 24),
+        Irq::new("TIM1_UP_TIM10", 25),
+        Irq::new("TIM1_TRG_COM_TIM11", 26),
+        Irq::new("TIM1_CC", 27),
+        Irq::new("TIM2", 28),
+        Irq::new("TIM3", 29),
+        Irq::new("TIM4", 30),
+        Irq::new("I2C1_EV", 31),
+        Irq::new("I2C1_ER", 32),
+        Irq::new("I2C2_EV", 33),
+        Irq::new("I2C2_ER", 34),
+        Irq::new("SPI1", 35),
+        Irq::new("SPI2", 36),
+        Irq::new("USART1", 37),
+        Irq::new("USART2", 38),
+        Irq::new("USART3", 39),
+        Irq::new("EXTI15_10", 40),
+        Irq::new("RTC_ALARM", 41),
+        Irq::new("OTG_FS_WKUP", 42),
+        Irq::new("TIM8_BRK_TIM12", 43),
+        Irq::new("TIM8_UP_TIM13", 44),
+        Irq::new("TIM8_TRG_COM_TIM14", 45),
+        Irq::new("TIM8_CC", 46),
+        Irq::new("DMA1_STREAM7", 47),
+        Irq::new("FSMC", 48),
+        Irq::new("SDIO", 49),
+        Irq::new("TIM5", 50),
+        Irq::new("SPI3", 51),
+        Irq::new("UART4", 52),
+        Irq::new("UART5", 53),
+        Irq::new("TIM6_DAC", 54),
+        Irq::new("TIM7", 55),
+        Irq::new("DMA2_STREAM0", 56),
+        Irq::new("DMA2_STREAM1", 57),
+        Irq::new("DMA2_STREAM2", 58),
+        Irq::new("DMA2_STREAM3", 59),
+        Irq::new("DMA2_STREAM4", 60),
+        Irq::new("ETH", 61),
+        Irq::new("ETH_WKUP", 62),
+        Irq::new("CAN2_TX", 63),
+        Irq::new("CAN2_RX0", 64),
+        Irq::new("CAN2_RX1", 65),
+        Irq::new("CAN2_SCE", 66),
+        Irq::new("OTG_FS", 67),
+        Irq::new("DMA2_STREAM5", 68),
+        Irq::new("DMA2_STREAM6", 69),
+        Irq::new("DMA2_STREAM7", 70),
+        Irq::new("USART6", 71),
+        Irq::new("I2C3_EV", 72),
+        Irq::new("I2C3_ER", 73),
+        Irq::new("OTG_HS_EP1_OUT", 74),
+        Irq::new("OTG_HS_EP1_IN", 75),
+        Irq::new("OTG_HS_WKUP", 76),
+        Irq::new("OTG_HS", 77),
+        Irq::new("DCMI", 78),
+        Irq::new("CRYP", 79),
+        Irq::new("HASH_RNG", 80),
+        Irq::new("FPU", 81),
+        Irq::new("LTDC", 88),
+        Irq::new("LTDC_ER", 89),
+    ]),
+    nvic_prio_bits: 4,
+    svd_path: None,
+    config_path: None,
+};
+
+/// STM32F401xB/C and STM32F401xD/E (RM0368).
+const STM32F401: Chip = Chip {
+    name: Cow::Borrowed("stm32f401"),
+    irqs: Cow::Borrowed(&[
+        Irq::new("WWDG", 0),
+        Irq::new("PVD", 1),
+        Irq::new("TAMP_STAMP", 2),
+        Irq::new("RTC_WKUP", 3),
+        Irq::new("FLASH", 4),
+        Irq::new("RCC", 5),
+        Irq::new("EXTI0", 6),
+        Irq::new("EXTI1", 7),
+        Irq::new("EXTI2", 8),
+        Irq::new("EXTI3", 9),
+        Irq::new("EXTI4", 10),
+        Irq::new("DMA1_STREAM0", 11),
+        Irq::new("DMA1_STREAM1", 12),
+        Irq::new("DMA1_STREAM2", 13),
+        Irq::new("DMA1_STREAM3", 14),
+        Irq::new("DMA1_STREAM4", 15),
+        Irq::new("DMA1_STREAM5", 16),
+        Irq::new("DMA1_STREAM6", 17),
+        Irq::new("ADC", 18),
+        Irq::new("EXTI9_5", 23),
+        Irq::new("TIM1_BRK_TIM9", 24),
+        Irq::new("TIM1_UP_TIM10", 25),
+        Irq::new("TIM1_TRG_COM_TIM11", 26),
+        Irq::new("TIM1_CC", 27),
+        Irq::new("TIM2", 28),
+        Irq::new("TIM3", 29),
+        Irq::new("TIM4", 30),
+        Irq::new("I2C1_EV", 31),
+        Irq::new("I2C1_ER", 32),
+        Irq::new("I2C2_EV", 33),
+        Irq::new("I2C2_ER", 34),
+        Irq::new("SPI1", 35),
+        Irq::new("SPI2", 36),
+        Irq::new("USART1", 37),
+        Irq::new("USART2", 38),
+        Irq::new("EXTI15_10", 40),
+        Irq::new("RTC_ALARM", 41),
+        Irq::new("OTG_FS_WKUP", 42),
+        Irq::new("DMA1_STREAM7", 47),
+        Irq::new("SDIO", 49),
+        Irq::new("TIM5", 50),
+        Irq::new("SPI3", 51),
+        Irq::new("DMA2_STREAM0", 56),
+        Irq::new("DMA2_STREAM1", 57),
+        Irq::new("DMA2_STREAM2", 58),
+        Irq::new("DMA2_STREAM3", 59),
+        Irq::new("DMA2_STREAM4", 60),
+        Irq::new("OTG_FS", 67),
+        Irq::new("DMA2_STREAM5", 68),
+        Irq::new("DMA2_STREAM6", 69),
+        Irq::new("DMA2_STREAM7", 70),
+        Irq::new("USART6", 71),
+        Irq::new("I2C3_EV", 72),
+        Irq::new("I2C3_ER", 73),
+        Irq::new("FPU", 81),
+        Irq::new("SPI4", 84),
+    ]),
+    nvic_prio_bits: 4,
+    svd_path: None,
+    config_path: None,
+};
+
+/// STM32F405xx (RM0090).
+const STM32F405: Chip = Chip {
+    name: Cow::Borrowed("stm32f405"),
+    irqs: Cow::Borrowed(&[
+        Irq::new("WWDG", 0),
+        Irq::new("PVD", 1),
+        Irq::new("TAMP_STAMP", 2),
+        Irq::new("RTC_WKUP", 3),
+        Irq::new("FLASH", 4),
+        Irq::new("RCC", 5),
+        Irq::new("EXTI0", 6),
+        Irq::new("EXTI1", 7),
+        Irq::new("EXTI2", 8),
+        Irq::new("EXTI3", 9),
+        Irq::new("EXTI4", 10),
+        Irq::new("DMA1_STREAM0", 11),
+        Irq::new("DMA1_STREAM1", 12),
+        Irq::new("DMA1_STREAM2", 13),
+        Irq::new("DMA1_STREAM3", 14),
+        Irq::new("DMA1_STREAM4", 15),
+        Irq::new("DMA1_STREAM5", 16),
+        Irq::new("DMA1_STREAM6", 17),
+        Irq::new("ADC", 18),
+        Irq::new("CAN1_TX", 19),
+        Irq::new("CAN1_RX0", 20),
+        Irq::new("CAN1_RX1", 21),
+        Irq::new("CAN1_SCE", 22),
+        Irq::new("EXTI9_5", 23),
+        Irq::new("TIM1_BRK_TIM9", 24),
+        Irq::new("TIM1_UP_TIM10", 25),
+        Irq::new("TIM1_TRG_COM_TIM11", 26),
+        Irq::new("TIM1_CC", 27),
+        Irq::new("TIM2", 28),
+        Irq::new("TIM3", 29),
+        Irq::new("TIM4", 30),
+        Irq::new("I2C1_EV", 31),
+        Irq::new("I2C1_ER", 32),
+        Irq::new("I2C2_EV", 33),
+        Irq::new("I2C2_ER", 34),
+        Irq::new("SPI1", 35),
+        Irq::new("SPI2", 36),
+        Irq::new("USART1", 37),
+        Irq::new("USART2", 38),
+        Irq::new("USART3", 39),
+        Irq::new("EXTI15_10", 40),
+        Irq::new("RTC_ALARM", 41),
+        Irq::new("OTG_FS_WKUP", 42),
+        Irq::new("TIM8_BRK_TIM12", 43),
+        Irq::new("TIM8_UP_TIM13", 44),
+        Irq::new("TIM8_TRG_COM_TIM14", 45),
+        Irq::new("TIM8_CC", 46),
+        Irq::new("DMA1_STREAM7", 47),
+        Irq::new("FSMC", 48),
+        Irq::new("SDIO", 49),
+        Irq::new("TIM5", 50),
+        Irq::new("SPI3", 51),
+        Irq::new("UART4", 52),
+        Irq::new("UART5", 53),
+        Irq::new("TIM6_DAC", 54),
+        Irq::new("TIM7", 55),
+        Irq::new("DMA2_STREAM0", 56),
+        Irq::new("DMA2_STREAM1", 57),
+        Irq::new("DMA2_STREAM2", 58),
+        Irq::new("DMA2_STREAM3", 59),
+        Irq::new("DMA2_STREAM4", 60),
+        Irq::new("CAN2_TX", 63),
+        Irq::new("CAN2_RX0", 64),
+        Irq::new("CAN2_RX1", 65),
+        Irq::new("CAN2_SCE", 66),
+        Irq::new("OTG_FS", 67),
+        Irq::new("DMA2_STREAM5", 68),
+        Irq::new("DMA2_STREAM6", 69),
+        Irq::new("DMA2_STREAM7", 70),
+        Irq::new("USART6", 71),
+        Irq::new("I2C3_EV", 72),
+        Irq::new("I2C3_ER", 73),
+        Irq::new("OTG_HS_EP1_OUT", 74),
+        Irq::new("OTG_HS_EP1_IN", 75),
+        Irq::new("OTG_HS_WKUP", 76),
+        Irq::new("OTG_HS", 77),
+        Irq::new("HASH_RNG", 80),
+        Irq::new("FPU", 81),
+    ]),
+    nvic_prio_bits: 4,
+    svd_path: None,
+    config_path: None,
+};
+
+/// STM32F407xx (RM0090).
+const STM32F407: Chip = Chip {
+    name: Cow::Borrowed("stm32f407"),
+    irqs: Cow::Borrowed(&[
+        Irq::new("WWDG", 0),
+        Irq::new("PVD", 1),
+        Irq::new("TAMP_STAMP", 2),
+        Irq::new("RTC_WKUP", 3),
+        Irq::new("FLASH", 4),
+        Irq::new("RCC", 5),
+        Irq::new("EXTI0", 6),
+        Irq::new("EXTI1", 7),
+        Irq::new("EXTI2", 8),
+        Irq::new("EXTI3", 9),
+        Irq::new("EXTI4", 10),
+        Irq::new("DMA1_STREAM0", 11),
+        Irq::new("DMA1_STREAM1", 12),
+        Irq::new("DMA1_STREAM2", 13),
+        Irq::new("DMA1_STREAM3", 14),
+        Irq::new("DMA1_STREAM4", 15),
+        Irq::new("DMA1_STREAM5", 16),
+        Irq::new("DMA1_STREAM6", 17),
+        Irq::new("ADC", 18),
+        Irq::new("CAN1_TX", 19),
+        Irq::new("CAN1_RX0", 20),
+        Irq::new("CAN1_RX1", 21),
+        Irq::new("CAN1_SCE", 22),
+        Irq::new("EXTI9_5", 23),
+        Irq::new("TIM1_BRK_TIM9", 24),
+        Irq::new("TIM1_UP_TIM10", 25),
+        Irq::new("TIM1_TRG_COM_TIM11", 26),
+        Irq::new("TIM1_CC", 27),
+        Irq::new("TIM2", 28),
+        Irq::new("TIM3", 29),
+        Irq::new("TIM4", 30),
+        Irq::new("I2C1_EV", 31),
+        Irq::new("I2C1_ER", 32),
+        Irq::new("I2C2_EV", 33),
+        Irq::new("I2C2_ER", 34),
+        Irq::new("SPI1", 35),
+        Irq::new("SPI2", 36),
+        Irq::new("USART1", 37),
+        Irq::new("USART2", 38),
+        Irq::new("USART3", 39),
+        Irq::new("EXTI15_10", 40),
+        Irq::new("RTC_ALARM", 41),
+        Irq::new("OTG_FS_WKUP", 42),
+        Irq::new("TIM8_BRK_TIM12", 43),
+        Irq::new("TIM8_UP_TIM13", 44),
+        Irq::new("TIM8_TRG_COM_TIM14", 45),
+        Irq::new("TIM8_CC", 46),
+        Irq::new("DMA1_STREAM7", 47),
+        Irq::new("FSMC", 48),
+        Irq::new("SDIO", 49),
+        Irq::new("TIM5", 50),
+        Irq::new("SPI3", 51),
+        Irq::new("UART4", 52),
+        Irq::new("UART5", 53),
+        Irq::new("TIM6_DAC", 54),
+        Irq::new("TIM7", 55),
+        Irq::new("DMA2_STREAM0", 56),
+        Irq::new("DMA2_STREAM1", 57),
+        Irq::new("DMA2_STREAM2", 58),
+        Irq::new("DMA2_STREAM3", 59),
+        Irq::new("DMA2_STREAM4", 60),
+        Irq::new("ETH", 61),
+        Irq::new("ETH_WKUP", 62),
+        Irq::new("CAN2_TX", 63),
+        Irq::new("CAN2_RX0", 64),
+        Irq::new("CAN2_RX1", 65),
+        Irq::new("CAN2_SCE", 66),
+        Irq::new("OTG_FS", 67),
+        Irq::new("DMA2_STREAM5", 68),
+        Irq::new("DMA2_STREAM6", 69),
+        Irq::new("DMA2_STREAM7", 70),
+        Irq::new("USART6", 71),
+        Irq::new("I2C3_EV", 72),
+        Irq::new("I2C3_ER", 73),
+        Irq::new("OTG_HS_EP1_OUT", 74),
+        Irq::new("OTG_HS_EP1_IN", 75),
+        Irq::new("OTG_HS_WKUP", 76),
+        Irq::new("OTG_HS", 77),
+        Irq::new("DCMI", 78),
+        Irq::new("HASH_RNG", 80),
+        Irq::new("FPU", 81),
+    ]),
+    nvic_prio_bits: 4,
+    svd_path: None,
+    config_path: None,
+};
+
+/// STM32F411xC/E (RM0383).
+const STM32F411: Chip = Chip {
+    name: Cow::Borrowed("stm32f411"),
+    irqs: Cow::Borrowed(&[
+        Irq::new("WWDG", 0),
+        Irq::new("PVD", 1),
+        Irq::new("TAMP_STAMP", 2),
+        Irq::new("RTC_WKUP", 3),
+        Irq::new("FLASH", 4),
+        Irq::new("RCC", 5),
+        Irq::new("EXTI0", 6),
+        Irq::new("EXTI1", 7),
+        Irq::new("EXTI2", 8),
+        Irq::new("EXTI3", 9),
+        Irq::new("EXTI4", 10),
+        Irq::new("DMA1_STREAM0", 11),
+        Irq::new("DMA1_STREAM1", 12),
+        Irq::new("DMA1_STREAM2", 13),
+        Irq::new("DMA1_STREAM3", 14),
+        Irq::new("DMA1_STREAM4", 15),
+        Irq::new("DMA1_STREAM5", 16),
+        Irq::new("DMA1_STREAM6", 17),
+        Irq::new("ADC", 18),
+        Irq::new("EXTI9_5", 23),
+        Irq::new("TIM1_BRK_TIM9", 24),
+        Irq::new("TIM1_UP_TIM10", 25),
+        Irq::new("TIM1_TRG_COM_TIM11", 26),
+        Irq::new("TIM1_CC", 27),
+        Irq::new("TIM2", 28),
+        Irq::new("TIM3", 29),
+        Irq::new("TIM4", 30),
+        Irq::new("I2C1_EV", 31),
+        Irq::new("I2C1_ER", 32),
+        Irq::new("I2C2_EV", 33),
+        Irq::new("I2C2_ER", 34),
+        Irq::new("SPI1", 35),
+        Irq::new("SPI2", 36),
+        Irq::new("USART1", 37),
+        Irq::new("USART2", 38),
+        Irq::new("EXTI15_10", 40),
+        Irq::new("RTC_ALARM", 41),
+        Irq::new("OTG_FS_WKUP", 42),
+        Irq::new("DMA1_STREAM7", 47),
+        Irq::new("SDIO", 49),
+        Irq::new("TIM5", 50),
+        Irq::new("SPI3", 51),
+        Irq::new("DMA2_STREAM0", 56),
+        Irq::new("DMA2_STREAM1", 57),
+        Irq::new("DMA2_STREAM2", 58),
+        Irq::new("DMA2_STREAM3", 59),
+        Irq::new("DMA2_STREAM4", 60),
+        Irq::new("OTG_FS", 67),
+        Irq::new("DMA2_STREAM5", 68),
+        Irq::new("DMA2_STREAM6", 69),
+        Irq::new("DMA2_STREAM7", 70),
+        Irq::new("USART6", 71),
+        Irq::new("I2C3_EV", 72),
+        Irq::new("I2C3_ER", 73),
+        Irq::new("FPU", 81),
+        Irq::new("SPI4", 84),
+        Irq::new("SPI5", 85),
+    ]),
+    nvic_prio_bits: 4,
+    svd_path: None,
+    config_path: None,
+};
+
+/// STM32F427xx and STM32F429xx (RM0090).
+const STM32F429: Chip = Chip {
+    name: Cow::Borrowed("stm32f429"),
+    irqs: Cow::Borrowed(&[
+        Irq::new("WWDG", 0),
+        Irq::new("PVD", 1),
+        Irq::new("TAMP_STAMP", 2),
+        Irq::new("RTC_WKUP", 3),
+        Irq::new("FLASH", 4),
+        Irq::new("RCC", 5),
+        Irq::new("EXTI0", 6),
+        Irq::new("EXTI1", 7),
+        Irq::new("EXTI2", 8),
+        Irq::new("EXTI3", 9),
+        Irq::new("EXTI4", 10),
+        Irq::new("DMA1_STREAM0", 11),
+        Irq::new("DMA1_STREAM1", 12),
+        Irq::new("DMA1_STREAM2", 13),
+        Irq::new("DMA1_STREAM3", 14),
+        Irq::new("DMA1_STREAM4", 15),
+        Irq::new("DMA1_STREAM5", 16),
+        Irq::new("DMA1_STREAM6", 17),
+        Irq::new("ADC", 18),
+        Irq::new("CAN1_TX", 19),
+        Irq::new("CAN1_RX0", 20),
+        Irq::new("CAN1_RX1", 21),
+        Irq::new("CAN1_SCE", 22),
+        Irq::new("EXTI9_5", 23),
+        Irq::new("TIM1_BRK_TIM9", 24),
+        Irq::new("TIM1_UP_TIM10", 25),
+        Irq::new("TIM1_TRG_COM_TIM11", 26),
+        Irq::new("TIM1_CC", 27),
+        Irq::new("TIM2", 28),
+        Irq::new("TIM3", 29),
+        Irq::new("TIM4", 30),
+        Irq::new("I2C1_EV", 31),
+        Irq::new("I2C1_ER", 32),
+        Irq::new("I2C2_EV", 33),
+        Irq::new("I2C2_ER", 34),
+        Irq::new("SPI1", 35),
+        Irq::new("SPI2", 36),
+        Irq::new("USART1", 37),
+        Irq::new("USART2", 38),
+        Irq::new("USART3", 39),
+        Irq::new("EXTI15_10", 40),
+        Irq::new("RTC_ALARM", 41),
+        Irq::new("OTG_FS_WKUP", 42),
+        Irq::new("TIM8_BRK_TIM12", 43),
+        Irq::new("TIM8_UP_TIM13", 44),
+        Irq::new("TIM8_TRG_COM_TIM14", 45),
+        Irq::new("TIM8_CC", 46),
+        Irq::new("DMA1_STREAM7", 47),
+        Irq::new("FMC", 48),
+        Irq::new("SDIO", 49),
+        Irq::new("TIM5", 50),
+        Irq::new("SPI3", 51),
+        Irq::new("UART4", 52),
+        Irq::new("UART5", 53),
+        Irq::new("TIM6_DAC", 54),
+        Irq::new("TIM7", 55),
+        Irq::new("DMA2_STREAM0", 56),
+        Irq::new("DMA2_STREAM1", 57),
+        Irq::new("DMA2_STREAM2", 58),
+        Irq::new("DMA2_STREAM3", 59),
+        Irq::new("DMA2_STREAM4", 60),
+        Irq::new("ETH", 61),
+        Irq::new("ETH_WKUP", 62),
+        Irq::new("CAN2_TX", 63),
+        Irq::new("CAN2_RX0", 64),
+        Irq::new("CAN2_RX1", 65),
+        Irq::new("CAN2_SCE", 66),
+        Irq::new("OTG_FS", 67),
+        Irq::new("DMA2_STREAM5", 68),
+        Irq::new("DMA2_STREAM6", 69),
+        Irq::new("DMA2_STREAM7", 70),
+        Irq::new("USART6", 71),
+        Irq::new("I2C3_EV", 72),
+        Irq::new("I2C3_ER", 73),
+        Irq::new("OTG_HS_EP1_OUT", 74),
+        Irq::new("OTG_HS_EP1_IN", 75),
+        Irq::new("OTG_HS_WKUP", 76),
+        Irq::new("OTG_HS", 77),
+        Irq::new("DCMI", 78),
+        Irq::new("HASH_RNG", 80),
+        Irq::new("FPU", 81),
+        Irq::new("UART7", 82),
+        Irq::new("UART8", 83),
+        Irq::new("SPI4", 84),
+        Irq::new("SPI5", 85),
+        Irq::new("SPI6", 86),
+        Irq::new("SAI1", 87),
+        Irq::new("LTDC", 88),
+        Irq::new("LTDC_ER", 89),
+        Irq::new("DMA2D", 90),
+    ]),
+    nvic_prio_bits: 4,
+    svd_path: None,
+    config_path: None,
+};
+
+/// STM32F446xx (RM0390).
+const STM32F446: Chip = Chip {
+    name: Cow::Borrowed("stm32f446"),
+    irqs: Cow::Borrowed(&[
+        Irq::new("WWDG", 0),
+        Irq::new("PVD", 1),
+        Irq::new("TAMP_STAMP", 2),
+        Irq::new("RTC_WKUP", 3),
+        Irq::new("FLASH", 4),
+        Irq::new("RCC", 5),
+        Irq::new("EXTI0", 6),
+        Irq::new("EXTI1", 7),
+        Irq::new("EXTI2", 8),
+        Irq::new("EXTI3", 9),
+        Irq::new("EXTI4", 10),
+        Irq::new("DMA1_STREAM0", 11),
+        Irq::new("DMA1_STREAM1", 12),
+        Irq::new("DMA1_STREAM2", 13),
+        Irq::new("DMA1_STREAM3", 14),
+        Irq::new("DMA1_STREAM4", 15),
+        Irq::new("DMA1_STREAM5", 16),
+        Irq::new("DMA1_STREAM6", 17),
+        Irq::new("ADC", 18),
+        Irq::new("CAN1_TX", 19),
+        Irq::new("CAN1_RX0", 20),
+        Irq::new("CAN1_RX1", 21),
+        Irq::new("CAN1_SCE", 22),
+        Irq::new("EXTI9_5", 23),
+        Irq::new("TIM1_BRK_TIM9", 24),
+        Irq::new("TIM1_UP_TIM10", 25),
+        Irq::new("TIM1_TRG_COM_TIM11", 26),
+        Irq::new("TIM1_CC", 27),
+        Irq::new("TIM2", 28),
+        Irq::new("TIM3", 29),
+        Irq::new("TIM4", 30),
+        Irq::new("I2C1_EV", 31),
+        Irq::new("I2C1_ER", 32),
+        Irq::new("I2C2_EV", 33),
+        Irq::new("I2C2_ER", 34),
+        Irq::new("SPI1", 35),
+        Irq::new("SPI2", 36),
+        Irq::new("USART1", 37),
+        Irq::new("USART2", 38),
+        Irq::new("USART3", 39),
+        Irq::new("EXTI15_10", 40),
+        Irq::new("RTC_ALARM", 41),
+        Irq::new("OTG_FS_WKUP", 42),
+        Irq::new("TIM8_BRK_TIM12", 43),
+        Irq::new("TIM8_UP_TIM13", 44),
+        Irq::new("TIM8_TRG_COM_TIM14", 45),
+        Irq::new("TIM8_CC", 46),
+        Irq::new("DMA1_STREAM7", 47),
+        Irq::new("FMC", 48),
+        Irq::new("SDIO", 49),
+        Irq::new("TIM5", 50),
+        Irq::new("SPI3", 51),
+        Irq::new("UART4", 52),
+        Irq::new("UART5", 53),
+        Irq::new("TIM6_DAC", 54),
+        Irq::new("TIM7", 55),
+        Irq::new("DMA2_STREAM0", 56),
+        Irq::new("DMA2_STREAM1", 57),
+        Irq::new("DMA2_STREAM2", 58),
+        Irq::new("DMA2_STREAM3", 59),
+        Irq::new("DMA2_STREAM4", 60),
+        Irq::new("CAN2_TX", 63),
+        Irq::new("CAN2_RX0", 64),
+        Irq::new("CAN2_RX1", 65),
+        Irq::new("CAN2_SCE", 66),
+        Irq::new("OTG_FS", 67),
+        Irq::new("DMA2_STREAM5", 68),
+        Irq::new("DMA2_STREAM6", 69),
+        Irq::new("DMA2_STREAM7", 70),
+        Irq::new("USART6", 71),
+        Irq::new("I2C3_EV", 72),
+        Irq::new("I2C3_ER", 73),
+        Irq::new("OTG_HS_EP1_OUT", 74),
+        Irq::new("OTG_HS_EP1_IN", 75),
+        Irq::new("OTG_HS_WKUP", 76),
+        Irq::new("OTG_HS", 77),
+        Irq::new("DCMI", 78),
+        Irq::new("FPU", 81),
+        Irq::new("SPI4", 84),
+        Irq::new("SAI1", 87),
+        Irq::new("SAI2", 91),
+        Irq::new("QUADSPI", 92),
+        Irq::new("HDMI_CEC", 93),
+        Irq::new("SPDIF_RX", 94),
+        Irq::new("FMPI2C1_EV", 95),
+        Irq::new("FMPI2C1_ER", 96),
+    ]),
+    nvic_prio_bits: 4,
+    svd_path: None,
+    config_path: None,
+};
+
+/// STM32F745xx and STM32F746xx (RM0385).
+const STM32F7XX: Chip = Chip {
+    name: Cow::Borrowed("stm32f7xx"),
+    irqs: Cow::Borrowed(&[
+        Irq::new("WWDG", 0),
+        Irq::new("PVD", 1),
+        Irq::new("TAMP_STAMP", 2),
+        Irq::new("RTC_WKUP", 3),
+        Irq::new("FLASH", 4),
+        Irq::new("RCC", 5),
+        Irq::new("EXTI0", 6),
+        Irq::new("EXTI1", 7),
+        Irq::new("EXTI2", 8),
+        Irq::new("EXTI3", 9),
+        Irq::new("EXTI4", 10),
+        Irq::new("DMA1_STREAM0", 11),
+        Irq::new("DMA1_STREAM1", 12),
+        Irq::new("DMA1_STREAM2", 13),
+        Irq::new("DMA1_STREAM3", 14),
+        Irq::new("DMA1_STREAM4", 15),
+        Irq::new("DMA1_STREAM5", 16),
+        Irq::new("DMA1_STREAM6", 17),
+        Irq::new("ADC", 18),
+        Irq::new("CAN1_TX", 19),
+        Irq::new("CAN1_RX0", 20),
+        Irq::new("CAN1_RX1", 21),
+        Irq::new("CAN1_SCE", 22),
+        Irq::new("EXTI9_5", 23),
+        Irq::new("TIM1_BRK_TIM9", 24),
+        Irq::new("TIM1_UP_TIM10", 25),
+        Irq::new("TIM1_TRG_COM_TIM11", 26),
+        Irq::new("TIM1_CC", 27),
+        Irq::new("TIM2", 28),
+        Irq::new("TIM3", 29),
+        Irq::new("TIM4", 30),
+        Irq::new("I2C1_EV", 31),
+        Irq::new("I2C1_ER", 32),
+        Irq::new("I2C2_EV", 33),
+        Irq::new("I2C2_ER", 34),
+        Irq::new("SPI1", 35),
+        Irq::new("SPI2", 36),
+        Irq::new("USART1", 37),
+        Irq::new("USART2", 38),
+        Irq::new("USART3", 39),
+        Irq::new("EXTI15_10", 40),
+        Irq::new("RTC_ALARM", 41),
+        Irq::new("OTG_FS_WKUP", 42),
+        Irq::new("TIM8_BRK_TIM12", 43),
+        Irq::new("TIM8_UP_TIM13", 44),
+        Irq::new("TIM8_TRG_COM_TIM14", 45),
+        Irq::new("TIM8_CC", 46),
+        Irq::new("DMA1_STREAM7", 47),
+        Irq::new("FMC", 48),
+        Irq::new("SDMMC1", 49),
+        Irq::new("TIM5", 50),
+        Irq::new("SPI3", 51),
+        Irq::new("UART4", 52),
+        Irq::new("UART5", 53),
+        Irq::new("TIM6_DAC", 54),
+        Irq::new("TIM7", 55),
+        Irq::new("DMA2_STREAM0", 56),
+        Irq::new("DMA2_STREAM1", 57),
+        Irq::new("DMA2_STREAM2", 58),
+        Irq::new("DMA2_STREAM3", 59),
+        Irq::new("DMA2_STREAM4", 60),
+        Irq::new("ETH", 61),
+        Irq::new("ETH_WKUP", 62),
+        Irq::new("CAN2_TX", 63),
+        Irq::new("CAN2_RX0", 64),
+        Irq::new("CAN2_RX1", 65),
+        Irq::new("CAN2_SCE", 66),
+        Irq::new("OTG_FS", 67),
+        Irq::new("DMA2_STREAM5", 68),
+        Irq::new("DMA2_STREAM6", 69),
+        Irq::new("DMA2_STREAM7", 70),
+        Irq::new("USART6", 71),
+        Irq::new("I2C3_EV", 72),
+        Irq::new("I2C3_ER", 73),
+        Irq::new("OTG_HS_EP1_OUT", 74),
+        Irq::new("OTG_HS_EP1_IN", 75),
+        Irq::new("OTG_HS_WKUP", 76),
+        Irq::new("OTG_HS", 77),
+        Irq::new("DCMI", 78),
+        Irq::new("CRYP", 79),
+        Irq::new("HASH_RNG", 80),
+        Irq::new("FPU", 81),
+        Irq::new("UART7", 82),
+        Irq::new("UART8", 83),
+        Irq::new("SPI4", 84),
+        Irq::new("SPI5", 85),
+        Irq::new("SPI6", 86),
+        Irq::new("SAI1", 87),
+        Irq::new("LTDC", 88),
+        Irq::new("LTDC_ER", 89),
+        Irq::new("DMA2D", 90),
+        Irq::new("SAI2", 91),
+        Irq::new("QUADSPI", 92),
+        Irq::new("LPTIM1", 93),
+        Irq::new("CEC", 94),
+        Irq::new("I2C4_EV", 95),
+        Irq::new("I2C4_ER", 96),
+        Irq::new("SPDIF_RX", 97),
+    ]),
+    nvic_prio_bits: 4,
+    svd_path: None,
+    config_path: None,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exti_line(chip: &Chip, name: &str) -> Option<(u8, String)> {
+        chip.find_exti_line(name)
+            .map(|line| (line.line, line.vector.name.into_owned()))
+    }
+
+    #[test]
+    fn find_exti_line_maps_lines_to_shared_vectors() {
+        assert_eq!(exti_line(&STM32F4, "EXTI5"), Some((5, "EXTI9_5".into())));
+        assert_eq!(exti_line(&STM32F4, "EXTI9"), Some((9, "EXTI9_5".into())));
+        assert_eq!(
+            exti_line(&STM32F4, "EXTI10"),
+            Some((10, "EXTI15_10".into()))
+        );
+        assert_eq!(
+            exti_line(&STM32F4, "EXTI15"),
+            Some((15, "EXTI15_10".into()))
+        );
+    }
+
+    #[test]
+    fn find_exti_line_rejects_lines_with_own_vector() {
+        assert_eq!(exti_line(&STM32F4, "EXTI0"), None);
+        assert_eq!(exti_line(&STM32F4, "EXTI4"), None);
+        assert_eq!(exti_line(&STM32F4, "EXTI16"), None);
+    }
+
+    #[test]
+    fn find_exti_line_rejects_other_spellings() {
+        assert_eq!(exti_line(&STM32F4, "EXTI07"), None);
+        assert_eq!(exti_line(&STM32F4, "EXTI+7"), None);
+        assert_eq!(exti_line(&STM32F4, "EXTI"), None);
+        assert_eq!(exti_line(&STM32F4, "exti7"), None);
+    }
+
+    #[test]
+    fn find_exti_line_requires_shared_vector() {
+        const CHIP: Chip = Chip {
+            name: Cow::Borrowed("test"),
+            irqs: Cow::Borrowed(&[Irq::new("EXTI9_5", 23)]),
+            nvic_prio_bits: 4,
+            svd_path: None,
+            config_path: None,
+        };
+
+        assert_eq!(exti_line(&CHIP, "EXTI7"), Some((7, "EXTI9_5".into())));
+        assert_eq!(exti_line(&CHIP, "EXTI12"), None);
+    }
+}
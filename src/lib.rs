@@ -1,24 +1,47 @@
-//! Procedual macro implementations for the [`#[main]`](main)
-//! and [`#[handler(IRQ)]`](handler) attribute macro.
+//! Procedual macro implementations for the [`#[main]`](main),
+//! [`#[task]`](task), [`#[handler(IRQ)]`](handler) and
+//! [`#[exception(EXCEPTION)]`](exception) attribute macro.
 
+mod args;
+mod exception;
+mod exit_policy;
+mod exti;
+mod irqs;
+mod panic_policy;
+mod raw;
+// Also built without the `svd` feature for its unit tests.
+#[cfg(any(feature = "svd", test))]
+#[cfg_attr(not(feature = "svd"), allow(dead_code))]
+mod svd;
+mod task;
+mod trampoline;
+
+use args::{AttrArg, AttrArgs, CratePaths};
+use core::fmt::Display;
+use exit_policy::ExitPolicy;
+use irqs::{Chip, ExtiLine, Irq};
+use panic_policy::PanicPolicy;
 use proc_macro::TokenStream;
-use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
 use syn::{
-    parse_macro_input, AttributeArgs, ItemFn, Meta, NestedMeta, ReturnType, Signature, Type,
+    parse_macro_input, parse_quote, spanned::Spanned, Abi, Attribute, Error, Expr, ExprLit, FnArg,
+    Ident, ItemFn, Lit, LitStr, Meta, MetaNameValue, Path, Result, ReturnType, Signature, Type,
 };
 
 /// Mark a function as the entry function of the main task.
 ///
 /// The function should satisfy the following signature requirements:
-/// - Has one and only one argument of type `cortex_m::Peripherals`.
-/// - Returns `()` or `!`.
+/// - Has no argument, or one argument of type `cortex_m::Peripherals`,
+///   optionally followed by one argument of the `Peripherals` type of a
+///   device crate.
+/// - Returns `()`, `!` or `Result<(), E>` where `E: core::fmt::Debug`.
 /// - Is not `async`.
 /// - Is not `unsafe`.
 /// - Is not variadic.
 ///
 /// Example:
-/// ```rust
+/// ```ignore
 /// #[main]
 /// fn main(cp: cortex_m::Peripherals) {
 ///    /* initialize system */
@@ -26,190 +49,1214 @@ use syn::{
 /// }
 /// ```
 ///
+/// A main function that does not use the core peripherals can take no
+/// argument, in which case the trampoline drops them.
+///
 /// The macro works by generating a trampoline function to call the user
-/// defined main function. The macro expands to the following for the above
-/// example:
-///
-/// ```rust
-/// #[no_mangle]
-/// extern "C" fn __main_trampoline(arg: AtomicPtr<u8>) {
-///     let arg = arg.load(Ordering::SeqCst) as *mut cortex_m::Peripherals;
-///     let arg = unsafe { Box::from_raw(arg) };
+/// defined main function, and an assertion that the argument has the type
+/// of the peripherals that the kernel passes. The macro expands to the
+/// following for the above example:
+///
+/// ```ignore
+/// const _: fn() = || {
+///     trait Expected {}
+///     impl Expected for ::cortex_m::Peripherals {}
+///     fn assert_expected<T: Expected>() {}
+///     assert_expected::<cortex_m::Peripherals>();
+/// };
+///
+/// #[unsafe(no_mangle)]
+/// extern "C-unwind" fn __main_trampoline(arg: ::core::sync::atomic::AtomicPtr<u8>) {
+///     let arg = arg.load(::core::sync::atomic::Ordering::SeqCst)
+///         as *mut cortex_m::Peripherals;
+///     let arg = unsafe { ::alloc::boxed::Box::from_raw(arg) };
 ///     main(*arg)
 /// }
 /// ```
+///
+/// Before Rust 1.82, which introduced the `#[unsafe(..)]` form required by the
+/// 2024 edition, the generated attributes are written `#[no_mangle]`,
+/// `#[export_name = ".."]` and `#[link_section = ".."]` instead.
+///
+/// The main task and the kernel can be configured with the following options,
+/// which are checked at compile time and otherwise take the kernel defaults:
+/// - `stack_size = N`: the initial stack size of the main task in bytes,
+///   between 256 bytes and 1 MiB.
+/// - `priority = N`: the priority of the main task, where lower values are
+///   more urgent. The least urgent priority is reserved for the idle task.
+/// - `heap = N`: the size of the heap in bytes, between 1 KiB and 16 MiB.
+///
+/// Sizes must be multiples of 8 bytes, and can be given in KiB or MiB with a
+/// `K` or `M` suffix:
+///
+/// ```ignore
+/// #[main(stack_size = 8192, priority = 0, heap = 64K)]
+/// fn main(cp: cortex_m::Peripherals) {
+///    /* ... */
+/// }
+/// ```
+///
+/// The options are passed to the kernel through a static exported as
+/// `__hopter_main_config`:
+///
+/// ```ignore
+/// #[used]
+/// #[unsafe(export_name = "__hopter_main_config")]
+/// static __HOPTER_MAIN_CONFIG: ::hopter::config::MainConfig =
+///     ::hopter::config::MainConfig {
+///         stack_size: ::core::option::Option::Some(8192usize),
+///         priority: ::core::option::Option::Some(0u8),
+///         heap_size: ::core::option::Option::Some(65536usize),
+///     };
+/// ```
+///
+/// The device peripherals, such as those of a peripheral access crate, can
+/// be received as a second argument. The trampoline takes them by calling
+/// `Peripherals::take` on the argument type, so that the singleton is only
+/// handed out to the main function. The type can also be named with
+/// `device = path`, which is then asserted to be the argument type:
+///
+/// ```ignore
+/// #[main(device = stm32f4xx_hal::pac::Peripherals)]
+/// fn main(cp: cortex_m::Peripherals, dp: stm32f4xx_hal::pac::Peripherals) {
+///    /* ... */
+/// }
+/// ```
+///
+/// The trampoline then becomes:
+///
+/// ```ignore
+/// #[unsafe(no_mangle)]
+/// extern "C-unwind" fn __main_trampoline(arg: ::core::sync::atomic::AtomicPtr<u8>) {
+///     let device = <stm32f4xx_hal::pac::Peripherals>::take()
+///         .expect("device peripherals are taken before main");
+///     let arg = arg.load(::core::sync::atomic::Ordering::SeqCst)
+///         as *mut cortex_m::Peripherals;
+///     let arg = unsafe { ::alloc::boxed::Box::from_raw(arg) };
+///     main(*arg, device)
+/// }
+/// ```
+///
+/// The bring-up shared by the binaries of a board, such as clock, GPIO and
+/// console setup, can be moved into a board type implementing
+/// `hopter::BoardInit`, named with `board = path`. The trampoline then takes
+/// the device peripherals of type `BoardInit::Device`, initializes the board
+/// from both kinds of peripherals, and passes it as the only argument of the
+/// main function:
+///
+/// ```ignore
+/// #[main(board = my_bsp::Board)]
+/// fn main(board: my_bsp::Board) {
+///    /* ... */
+/// }
+/// ```
+///
+/// This expands to the following trampoline:
+///
+/// ```ignore
+/// #[unsafe(no_mangle)]
+/// extern "C-unwind" fn __main_trampoline(arg: ::core::sync::atomic::AtomicPtr<u8>) {
+///     let device = <<my_bsp::Board as ::hopter::BoardInit>::Device>::take()
+///         .expect("device peripherals are taken before main");
+///     let arg = arg.load(::core::sync::atomic::Ordering::SeqCst)
+///         as *mut ::cortex_m::Peripherals;
+///     let arg = unsafe { ::alloc::boxed::Box::from_raw(arg) };
+///     main(<my_bsp::Board as ::hopter::BoardInit>::init(*arg, device))
+/// }
+/// ```
+///
+/// A main function returning `Result<(), E>` can propagate errors with `?`.
+/// An error is printed through semihosting, or passed to the function given
+/// with `report = path`, which receives a `&dyn core::fmt::Debug`. The
+/// trampoline then applies the policy given with `on_error = policy`:
+/// - `halt`: the default. Mask all interrupts and sleep forever.
+/// - `reset`: reset the MCU.
+/// - `restart`: panic, so that the kernel recovers by restarting the main
+///   task. Only a main function without argument can be restarted, as the
+///   peripherals are handed out once.
+///
+/// ```ignore
+/// #[main(report = log_error, on_error = reset)]
+/// fn main(cp: cortex_m::Peripherals) -> Result<(), InitError> {
+///    /* ... */
+/// }
+/// ```
+///
+/// The trampoline then handles the returned value as follows:
+///
+/// ```ignore
+/// let result: ::core::result::Result<(), _> = {
+///     /* unbox the argument and call main */
+/// };
+/// if let ::core::result::Result::Err(error) = result {
+///     log_error(&error);
+///     ::cortex_m::peripheral::SCB::sys_reset();
+/// }
+/// ```
+///
+/// The generated code refers to the `hopter`, `cortex_m` and `alloc` crates,
+/// which are expected to be dependencies of the crate using the macro. A
+/// crate that wraps Hopter, such as a board support crate, can be named
+/// instead with `crate = path`. The generated code then refers to Hopter as
+/// `path`, and to `cortex_m` and `alloc` through the `path::cortex_m` and
+/// `path::alloc` re-exports, so that only the wrapper crate needs to be a
+/// dependency:
+///
+/// ```ignore
+/// #[main(crate = my_bsp::hopter)]
+/// fn main(cp: my_bsp::hopter::cortex_m::Peripherals) {
+///    /* ... */
+/// }
+/// ```
 #[proc_macro_attribute]
-pub fn main(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn main(attr: TokenStream, item: TokenStream) -> TokenStream {
     // Parse the `item` TokenStream into a Rust function.
     let main_func = parse_macro_input!(item as ItemFn);
 
-    check_main_function_signature(&main_func.sig);
+    // Parse the `attr` TokenStream into attribute arguments.
+    let attr_args = parse_macro_input!(attr as AttrArgs);
 
-    // Store the function's name.
-    let func_name = main_func.sig.ident.to_string();
+    // Report problems with both the signature and the attribute at once.
+    let mut errors = Errors::default();
 
-    // Generate the trampoline function string.
-    let trampoline = format!(
-        "\
-        #[no_mangle]\n\
-        extern \"C\" fn __main_trampoline(arg: core::sync::atomic::AtomicPtr<u8>) {{\n\
-            let arg = arg.load(core::sync::atomic::Ordering::SeqCst) as *mut cortex_m::Peripherals;\n\
-            let arg = unsafe {{ alloc::boxed::Box::from_raw(arg) }};\n\
-            {}(*arg)\n\
-        }}",
-        func_name
-    );
+    if let Err(error) = check_main_function_signature(&main_func.sig) {
+        errors.combine(error);
+    }
 
-    // Parse the trampoline string into a token stream.
-    let trampoline = syn::parse_str::<TokenStream2>(trampoline.as_str()).unwrap();
+    let args = match parse_main_args(&attr_args.0) {
+        Ok(args) => Some(args),
+        Err(error) => {
+            errors.combine(error);
+            None
+        }
+    };
 
-    // Output the trampoline followed by the original main function.
+    match &args {
+        // The board is initialized from both kinds of peripherals, and is
+        // then the only argument of the main function.
+        Some(MainArgs {
+            board: Some(board),
+            device,
+            ..
+        }) => {
+            if main_func.sig.inputs.is_empty() {
+                errors.push(
+                    board,
+                    "`board` requires main function to receive the initialized board.",
+                );
+            }
+            if let Some(device) = device {
+                errors.push(
+                    device,
+                    "`device` cannot be used with `board`, which defines the \
+                    device peripherals.",
+                );
+            }
+            for extra in main_func.sig.inputs.iter().skip(1) {
+                errors.push(
+                    extra,
+                    "Main function with `board` only receives the initialized board.",
+                );
+            }
+        }
+        // The device peripherals type is checked against the second argument.
+        Some(MainArgs {
+            device: Some(device),
+            ..
+        }) if main_func.sig.inputs.len() < 2 => {
+            errors.push(
+                device,
+                "`device` requires main function to receive the device \
+                peripherals as the second argument.",
+            );
+        }
+        _ => {}
+    }
+
+    // Only errors returned by the main function are reported.
+    if !returns_result(&main_func.sig.output) {
+        for arg in attr_args.0.iter() {
+            if let AttrArg::NameValue { name, .. } = arg {
+                if name == "report" || name == "on_error" {
+                    errors.push(
+                        name,
+                        format!(
+                            "`{}` only applies to main function returning `Result`.",
+                            name
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    // A restarted main task would take the singleton peripherals again.
+    if let (
+        Some(MainArgs {
+            on_error: Some(ExitPolicy::Restart),
+            ..
+        }),
+        Some(input),
+    ) = (&args, main_func.sig.inputs.first())
+    {
+        errors.push(
+            input,
+            "Main function restarted with `on_error = restart` cannot take \
+            arguments, as the peripherals are only handed out once.",
+        );
+    }
+
+    if let Err(error) = errors.finish() {
+        return error_with_item(error, &main_func);
+    }
+
+    let args = args.unwrap();
+    let CratePaths {
+        hopter,
+        cortex_m,
+        alloc,
+        ..
+    } = &args.crates;
+
+    // Store the function's name. Generated code carries its span, so that
+    // errors in the expansion point back to the user function.
+    let func_name = &main_func.sig.ident;
+    let span = func_name.span();
+
+    let forwarded = ForwardedAttrs::new(&main_func);
+    let func_attrs = &forwarded.func;
+
+    // The argument type was checked to be a path, which must name the core
+    // peripherals, or the board if there is one.
+    let arg_ty = match main_func.sig.inputs.first() {
+        Some(FnArg::Typed(arg)) => Some(&arg.ty),
+        _ => None,
+    };
+    let type_assertion = match (arg_ty, &args.board) {
+        (Some(arg_ty), Some(board)) => Some(generate_type_assertion(
+            arg_ty,
+            "main function's argument",
+            &board.to_token_stream(),
+            &forwarded.cfg,
+        )),
+        (Some(arg_ty), None) => Some(generate_type_assertion(
+            arg_ty,
+            "main function's argument",
+            &quote!(#cortex_m::Peripherals),
+            &forwarded.cfg,
+        )),
+        (None, _) => None,
+    };
+
+    // The device peripherals are a singleton as well, which the trampoline
+    // takes once on behalf of the main function.
+    let device_ty = match (&args.board, main_func.sig.inputs.iter().nth(1)) {
+        (Some(board), _) => Some(quote! { <#board as #hopter::BoardInit>::Device }),
+        (None, Some(FnArg::Typed(arg))) => Some(arg.ty.to_token_stream()),
+        _ => None,
+    };
+    let device_subject = if args.board.is_some() {
+        "board's device peripherals"
+    } else {
+        "main function's second argument"
+    };
+    let device_assertion = match (&device_ty, &args.device) {
+        (Some(device_ty), Some(device)) => Some(generate_type_assertion(
+            device_ty,
+            device_subject,
+            &device.to_token_stream(),
+            &forwarded.cfg,
+        )),
+        _ => None,
+    };
+    let take_device = device_ty.map(|device_ty| {
+        quote_spanned! {span=>
+            let device = <#device_ty>::take()
+                .expect("device peripherals are taken before main");
+        }
+    });
+
+    // Without a board, the trampoline unboxes the argument as the declared
+    // type, so that a mismatch is only reported by the assertion. A main
+    // function without argument lets the trampoline drop the peripherals.
+    let (unboxed_ty, call_arg) = match (arg_ty, &args.board, &take_device) {
+        (None, _, _) => (quote_spanned! {span=> #cortex_m::Peripherals }, None),
+        (Some(_), Some(board), _) => (
+            quote_spanned! {span=> #cortex_m::Peripherals },
+            Some(quote_spanned! {span=>
+                <#board as #hopter::BoardInit>::init(*arg, device)
+            }),
+        ),
+        (Some(arg_ty), None, Some(_)) => (
+            arg_ty.to_token_stream(),
+            Some(quote_spanned! {span=> *arg, device }),
+        ),
+        (Some(arg_ty), None, None) => (
+            arg_ty.to_token_stream(),
+            Some(quote_spanned! {span=> *arg }),
+        ),
+    };
+
+    let mut unboxing_call = task::unboxing_call(func_name, &unboxed_ty, call_arg, alloc);
+
+    // An error returned by the main function is reported, and the system
+    // halts unless another policy is configured.
+    if returns_result(&main_func.sig.output) {
+        unboxing_call = exit_policy::handle_error(
+            unboxing_call,
+            args.on_error.as_ref().unwrap_or(&ExitPolicy::Halt),
+            args.report.as_ref(),
+            func_name,
+            &args.crates,
+        );
+    }
+
+    // The kernel looks up the configuration by symbol when booting, and
+    // falls back to its defaults for the settings left out.
+    let config = match (args.stack_size, args.priority, args.heap) {
+        (None, None, None) => None,
+        (stack_size, priority, heap_size) => {
+            let stack_size = option_tokens(stack_size);
+            let priority = option_tokens(priority);
+            let heap_size = option_tokens(heap_size);
+            let cfg_attrs = &forwarded.cfg;
+            let export_name = trampoline::unsafe_attr(
+                span,
+                quote_spanned! {span=> export_name = "__hopter_main_config" },
+            );
+            Some(quote_spanned! {span=>
+                #cfg_attrs
+                #[used]
+                #export_name
+                static __HOPTER_MAIN_CONFIG: #hopter::config::MainConfig =
+                    #hopter::config::MainConfig {
+                        stack_size: #stack_size,
+                        priority: #priority,
+                        heap_size: #heap_size,
+                    };
+            })
+        }
+    };
+
+    // Generate the trampoline function.
+    let no_mangle = trampoline::unsafe_attr(span, quote_spanned! {span=> no_mangle });
+    let trampoline = quote_spanned! {span=>
+        #func_attrs
+        #no_mangle
+        extern "C-unwind" fn __main_trampoline(arg: ::core::sync::atomic::AtomicPtr<u8>) {
+            #take_device
+            #unboxing_call
+        }
+    };
+
+    // Output the trampoline and the configuration followed by the original
+    // main function.
     quote! {
+        #type_assertion
+        #device_assertion
         #trampoline
+        #config
         #main_func
     }
     .into()
 }
 
-/// Mark a function as the handler function of an IRQ.
+/// Mark a function as the handler function of one or more IRQs.
 ///
 /// A handler function should satisfy the following signature requirements:
-/// - Has no argument.
+/// - Has no argument, or one argument of type `u16` receiving the IRQ number.
 /// - Returns `()`.
-/// - Has `extern "C"` ABI.
+/// - Has `extern "C"` or `extern "C-unwind"` ABI.
 /// - Is not `async`.
 /// - Is not variadic.
 ///
+/// A handler declared `extern "C-unwind"` may panic, in which case the panic
+/// unwinds through the generated code into Hopter's recovery path. The
+/// generated trampoline of the main function is `extern "C-unwind"` for the
+/// same reason.
+///
+/// A `C-unwind` handler can additionally be given a policy applied when it
+/// panics, with `on_panic = policy`, before the panic unwinds into the
+/// kernel:
+/// - `restart` only lets the kernel recover, so that the handler runs again
+///   on the next interrupt.
+/// - `disable_irq` masks the IRQs the handler is bound to. EXTI lines are
+///   masked in the EXTI peripheral, leaving the other lines of their shared
+///   vector enabled.
+/// - `reset` resets the MCU.
+/// - A path to a function such as `fn(irq: u16)` calls it with the number of
+///   the IRQ, e.g. to notify a supervisor task. EXTI lines pass the number of
+///   their shared vector.
+///
+/// ```ignore
+/// #[handler(TIM7, on_panic = disable_irq)]
+/// extern "C-unwind" fn tim7_handler() {
+///     /* a panic here masks TIM7 */
+/// }
+/// ```
+///
+/// The IRQ name is checked against the IRQs of the chip selected with one of
+/// the `stm32f401`, `stm32f405`, `stm32f407`, `stm32f411`, `stm32f429`,
+/// `stm32f446` or `stm32f7xx` cargo features. At most one of them can be
+/// enabled. Without any, a generic STM32F4 IRQ list is used. Alternatively,
+/// the `svd` feature reads the IRQ list from a CMSIS-SVD file pointed to by
+/// the `HOPTER_SVD` environment variable or by a top-level `svd = "..."`
+/// entry in a `hopter.toml` file next to the crate's `Cargo.toml`.
+///
 /// Example:
-/// ```rust
+/// ```ignore
 /// #[handler(TIM7)]
 /// extern "C" fn tim7_handler() {
 ///     /* handler logic */
 /// }
 /// ```
 ///
-/// The macro works by generating a trampoline function for the IRQ to call
+/// Listing several IRQs binds the same function to each of them. If the
+/// function has a `u16` parameter, it receives the number of the IRQ that
+/// fired:
+///
+/// ```ignore
+/// #[handler(DMA1_STREAM0, DMA1_STREAM1, DMA1_STREAM2)]
+/// extern "C" fn dma1_handler(irq: u16) {
+///     /* tell the streams apart by `irq` */
+/// }
+/// ```
+///
+/// The macro also generates a module named after the handler function which
+/// describes the IRQs the handler is bound to, so that drivers can configure
+/// the exact NVIC lines without repeating the IRQ numbers. The module
+/// contains `IRQ_NUMBERS` and `IRQS` listing all bound IRQs, a constant named
+/// after each IRQ, and `IRQ_NUMBER` and `IRQ` if there is only one:
+///
+/// ```ignore
+/// // `tim7_handler::IRQ_NUMBER` is `55`.
+/// unsafe { cortex_m::peripheral::NVIC::unmask(tim7_handler::IRQ) };
+/// unsafe { cortex_m::peripheral::NVIC::unmask(dma1_handler::DMA1_STREAM1) };
+/// ```
+///
+/// The lines 5 to 15 of the EXTI peripheral share the `EXTI9_5` and
+/// `EXTI15_10` vectors. They can be bound individually as `EXTI5` to
+/// `EXTI15`. The macro then generates a dispatcher for the shared vector,
+/// which clears the pending bit of each pending line and calls the handler
+/// bound to it. A line cannot be bound twice, which is reported as a
+/// duplicated `__hopter_exti_lineN_bound` symbol when linking, and neither
+/// can a line whose shared vector is bound directly. The generated
+/// module describes such lines by the NVIC number of their shared vector.
+/// Since all lines of a vector have that number in common, a handler bound
+/// to a line cannot receive the IRQ number, and the `priority` and `unmask`
+/// options do not apply. The shared vector is configured through the NVIC
+/// directly instead.
+///
+/// ```ignore
+/// #[handler(EXTI7)]
+/// extern "C" fn button_handler() {
+///     /* the pending bit is already cleared */
+/// }
+/// ```
+///
+/// The desired NVIC priority of the IRQs can be given with `priority = N`.
+/// Like task priorities, lower values are more urgent: `N` ranges from 0, the
+/// most urgent level, to one less than the number of levels the NVIC
+/// implements, e.g. 15 with 4 priority bits. Adding `unmask` additionally
+/// enables the IRQs. The kernel applies both during boot, so drivers need
+/// not program the NVIC themselves:
+///
+/// ```ignore
+/// #[handler(TIM7, priority = 12, unmask)]
+/// extern "C" fn tim7_handler() {
+///     /* handler logic */
+/// }
+/// ```
+///
+/// Since handlers enter the kernel through `fast_irq_entry`, they must be
+/// masked by the BASEPRI value of `0x80` that Hopter raises to in its
+/// critical sections, unless the handler is `raw`. The priority thus cannot
+/// be more urgent than the level that `0x80` stands for, which is 8 with 4
+/// priority bits and 4 with 3 priority bits.
+///
+/// Like [`#[main]`](main), the macro accepts `crate = path` to resolve Hopter
+/// and `cortex_m` through a crate that re-exports them, e.g.
+/// `#[handler(TIM7, crate = my_bsp::hopter)]`.
+///
+/// The routine through which the trampoline enters the kernel can be chosen
+/// with `entry = flavor`, where the flavor is `default` for
+/// `hopter::interrupt::default::fast_irq_entry`, `full_context` for
+/// `hopter::interrupt::full_context::irq_entry`, or `irq_stack` for
+/// `hopter::interrupt::irq_stack::irq_entry`. A custom entry routine can be
+/// given by its path instead. It is branched to with the address of the
+/// handler function in `r0`. EXTI lines always use the `default` entry of
+/// their shared vector.
+///
+/// ```ignore
+/// #[handler(USART2, entry = full_context)]
+/// extern "C" fn usart2_handler() {
+///     /* handler logic */
+/// }
+/// ```
+///
+/// With `raw`, the handler function is exported directly as the vector and
+/// runs without entering the kernel through `fast_irq_entry`. A raw handler
+/// must be bound to a single IRQ, take no parameter, be `extern "C"` rather
+/// than `extern "C-unwind"`, and be declared `unsafe` as a marker that it
+/// must not call any Hopter API. The macro rejects paths into Hopter within
+/// its body. Since it does not enter the kernel, its priority may be more
+/// urgent than that:
+///
+/// ```ignore
+/// #[handler(TIM7, raw, priority = 0)]
+/// unsafe extern "C" fn tim7_handler() {
+///     /* no Hopter API here */
+/// }
+/// ```
+///
+/// Latency-critical handlers can be executed from RAM with
+/// `section = ".name"`, which places the handler function and its
+/// trampolines into the given link section. The linker script must define
+/// the `__sname` and `__ename` symbols around the section, e.g. `__sramfunc`
+/// and `__eramfunc` for `.ramfunc`, and linking fails if they are missing.
+///
+/// ```ignore
+/// #[handler(TIM1_CC, priority = 8, section = ".ramfunc")]
+/// extern "C" fn motor_handler() {
+///     /* handler logic */
+/// }
+/// ```
+///
+/// The `#[cfg]`, `#[cfg_attr]`, `#[doc]` and `#[link_section]` attributes of
+/// the handler function are copied onto the generated trampolines, so that a
+/// handler disabled by `#[cfg]` takes its trampolines along with it.
+///
+/// The macro works by generating a trampoline function for each IRQ to call
 /// the user defined handler function. For example, for `TIM7`, the generated
 /// trampoline looks like below:
 ///
-/// ```rust
-/// #[naked]
-/// #[export_name = "TIM7"]
+/// ```ignore
+/// #[unsafe(naked)]
+/// #[unsafe(export_name = "TIM7")]
 /// unsafe extern "C" fn __tim7_entry() {
-///     core::arch::asm!(
+///     ::core::arch::naked_asm!(
 ///         "ldr r0, ={handler_func}",
-///         "b {fast_irq_entry}",
-///         fast_irq_entry = sym hopter::interrupt::default::fast_irq_entry,
+///         "b {irq_entry}",
+///         irq_entry = sym ::hopter::interrupt::default::fast_irq_entry,
 ///         handler_func = sym tim7_handler,
-///         options(noreturn)
 ///     )
 /// }
 /// ```
+///
+/// Naked functions take the above form on Rust 1.88 or later. On older
+/// nightly compilers, they are `#[naked]` functions, which require
+/// `#![feature(naked_functions)]`. Their body is `naked_asm!` from Rust 1.84,
+/// and `asm!` with `options(noreturn)` before. On older stable compilers, or
+/// with the `global_asm` feature of this crate, the trampoline is instead
+/// written in a `global_asm!` block, which works on any stable compiler.
+///
+/// If the handler function receives the IRQ number, the trampoline instead
+/// refers to a shim function such as `extern "C" fn __tim7_shim() {
+/// tim7_handler(55) }`.
 #[proc_macro_attribute]
 pub fn handler(attr: TokenStream, item: TokenStream) -> TokenStream {
     // Parse the `item` TokenStream into a Rust function.
-    let handler_func = parse_macro_input!(item as ItemFn);
+    let mut handler_func = parse_macro_input!(item as ItemFn);
+
+    // Parse the `attr` TokenStream into attribute arguments.
+    let attr_args = parse_macro_input!(attr as AttrArgs);
+
+    let mut errors = Errors::default();
+
+    if let Err(error) = check_handler_function_signature(&handler_func.sig) {
+        errors.combine(error);
+    }
+
+    let chip = match irqs::selected_chip() {
+        Ok(chip) => Some(chip),
+        Err(message) => {
+            errors.combine(Error::new(Span::call_site(), message));
+            None
+        }
+    };
+
+    let args = chip
+        .as_ref()
+        .and_then(|chip| match parse_handler_args(&attr_args.0, chip) {
+            Ok(args) => Some(args),
+            Err(error) => {
+                errors.combine(error);
+                None
+            }
+        });
+
+    if let Some(HandlerArgs {
+        raw: true, crates, ..
+    }) = &args
+    {
+        if let Err(error) = raw::check_raw_handler(&handler_func, &crates.hopter) {
+            errors.combine(error);
+        }
+    }
+
+    // The lines of a shared vector all have the NVIC number of that vector.
+    if let (Some(HandlerArgs { exti_lines, .. }), Some(input)) =
+        (&args, handler_func.sig.inputs.first())
+    {
+        if !exti_lines.is_empty() {
+            errors.push(
+                input,
+                "Handler bound to an EXTI line cannot receive the IRQ number, which \
+                all lines of a shared vector have in common. Bind a function to \
+                each line instead.",
+            );
+        }
+    }
 
-    // // Parse the `attr` TokenStream into attribute arguments.
-    let attr_args = parse_macro_input!(attr as AttributeArgs);
+    // A panic only reaches the guard applying the policy if it may unwind
+    // out of the handler.
+    if let Some(HandlerArgs {
+        on_panic: Some(_), ..
+    }) = &args
+    {
+        let sig = &handler_func.sig;
+        let unwind_error =
+            "Handler with `on_panic` must be `extern \"C-unwind\"`, so that its panics unwind.";
+        match &sig.abi {
+            Some(Abi {
+                name: Some(name), ..
+            }) if name.value() == "C-unwind" => {}
+            Some(abi) => errors.push(abi, unwind_error),
+            None => errors.push(sig.fn_token, unwind_error),
+        }
+    }
+
+    // The `section` option would conflict with a placement given by hand.
+    if let Some(HandlerArgs {
+        section: Some(_), ..
+    }) = &args
+    {
+        if let Some(attr) = handler_func
+            .attrs
+            .iter()
+            .find(|attr| link_section_value(attr).is_some())
+        {
+            errors.push(
+                attr,
+                "The `section` option cannot be combined with `#[link_section]`.",
+            );
+        }
+    }
 
-    check_handler_function_signature(&handler_func.sig);
+    if let Err(error) = errors.finish() {
+        return error_with_item(error, &handler_func);
+    }
+
+    // Without any error, both the chip and the arguments are known.
+    let (chip, args) = (chip.unwrap(), args.unwrap());
+
+    // Placing the handler function into the requested section also places
+    // the generated code there, as its `#[link_section]` gets forwarded.
+    if let Some(section) = &args.section {
+        handler_func.attrs.push(trampoline::unsafe_attr(
+            section.span(),
+            quote! { link_section = #section },
+        ));
+    }
 
-    let irq = parse_attribute_arg_to_irq(&attr_args);
+    // Rebuild the user crate when the SVD file the IRQ was validated against,
+    // the config file naming it, or the environment variable pointing to it
+    // changes. The catalog is read once per crate, and so is tracked by the
+    // first expansion in the crate only.
+    let svd_dependency = chip
+        .svd_path
+        .as_ref()
+        .filter(|_| irqs::take_svd_tracking())
+        .map(|path| {
+            let files = [Some(path), chip.config_path.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(|path| path.display().to_string());
+            quote! {
+                #(const _: &[u8] = ::core::include_bytes!(#files);)*
+                const _: ::core::option::Option<&str> = ::core::option_env!("HOPTER_SVD");
+            }
+        });
 
     // Store the handler function's name.
-    let func_name = handler_func.sig.ident.to_string();
-
-    // Generate the trampoline function string.
-    let trampoline = format!(
-        "\
-        #[naked]\n\
-        #[export_name = \"{}\"]\n\
-        unsafe extern \"C\" fn __{}_entry() {{\n\
-            core::arch::asm!(\n\
-                \"ldr r0, ={{handler_func}}\",\n\
-                \"b {{fast_irq_entry}}\",\n\
-                fast_irq_entry = sym hopter::interrupt::default::fast_irq_entry,\n\
-                handler_func = sym {},\n\
-                options(noreturn)\n\
-            )\n\
-        }}",
-        irq,
-        irq.to_lowercase(),
-        func_name
-    );
-
-    // Parse the trampoline string into a token stream.
-    let trampoline = syn::parse_str::<TokenStream2>(trampoline.as_str()).unwrap();
+    let func_name = &handler_func.sig.ident;
+    let span = func_name.span();
+
+    // Whether the handler wants to know which IRQ triggered it.
+    let takes_irq_number = !handler_func.sig.inputs.is_empty();
+
+    let hopter = &args.crates.hopter;
+    let irq_entry = &args.entry;
+
+    // Functions calling the handler share its ABI, so that a panic in a
+    // `C-unwind` handler unwinds through them into the kernel.
+    let abi = &handler_func.sig.abi;
+
+    // Generated items must vanish along with a `#[cfg]`-disabled handler.
+    let forwarded = ForwardedAttrs::new(&handler_func);
+    let func_attrs = &forwarded.func;
+
+    let mut trampolines = TokenStream2::new();
+
+    // A raw handler is exported as the vector itself, without trampoline.
+    if args.raw {
+        let irq_name = args.irqs[0].name.as_ref();
+        handler_func.attrs.push(trampoline::unsafe_attr(
+            span,
+            quote_spanned! {span=> export_name = #irq_name },
+        ));
+    }
+
+    for irq in args.irqs.iter().filter(|_| !args.raw) {
+        let exti_line = args
+            .exti_lines
+            .iter()
+            .find(|l| irq.name == format!("EXTI{}", l.line));
+
+        // Pass the number of the IRQ if the handler wants it, and apply the
+        // panic policy around the call.
+        let irq_number = takes_irq_number.then_some(irq.number);
+        let call = quote_spanned! {span=> #func_name(#irq_number) };
+        let call = match &args.on_panic {
+            Some(policy) => {
+                panic_policy::guard_call(call, policy, func_name, irq, exti_line, &args.crates)
+            }
+            None => call,
+        };
+
+        // EXTI lines are called by the dispatcher of their shared vector.
+        if let Some(line) = exti_line {
+            trampolines.extend(exti::generate_line_binding(
+                func_name, abi, call, line, hopter, &forwarded,
+            ));
+            continue;
+        }
+
+        let irq_name = irq.name.as_ref();
+        let irq_lowercase = irq.name.to_lowercase();
+        let entry_name = format_ident!("__{}_entry", irq_lowercase, span = span);
+
+        // A handler receiving the IRQ number or having a panic policy is
+        // called through a shim specific to the IRQ.
+        let target_func = if takes_irq_number || args.on_panic.is_some() {
+            let shim_name = format_ident!("__{}_shim", irq_lowercase, span = span);
+            trampolines.extend(quote_spanned! {span=>
+                #func_attrs
+                #abi fn #shim_name() {
+                    #call
+                }
+            });
+            shim_name
+        } else {
+            func_name.clone()
+        };
+
+        // Generate the trampoline function.
+        trampolines.extend(trampoline::generate_entry(
+            irq_name,
+            &entry_name,
+            &["ldr r0, ={handler_func}", "b {irq_entry}"],
+            quote_spanned! {span=>
+                irq_entry = sym #irq_entry,
+                handler_func = sym #target_func,
+            },
+            &forwarded,
+        ));
+    }
+
+    // A shared vector bound directly replaces the dispatcher of its lines,
+    // which thus count as bound as well.
+    for irq in args.irqs.iter() {
+        trampolines.extend(exti::claim_shared_vector(&irq.name, span, &forwarded.cfg));
+    }
+
+    let irq_module = generate_irq_module(&handler_func, &args, &forwarded.cfg);
+
+    let section_check = args
+        .section
+        .as_ref()
+        .map(|section| generate_section_check(section, span, &forwarded.cfg));
 
     // Output the trampoline followed by the original main function.
     quote! {
-        #trampoline
+        #svd_dependency
+        #section_check
+        #trampolines
+        #irq_module
         #handler_func
     }
     .into()
 }
 
+/// Mark a function as the handler function of a core Cortex-M exception.
+///
+/// The supported exceptions are `NMI`, `HardFault`, `MemManage`, `BusFault`,
+/// `UsageFault` and `DebugMonitor`. `SVCall`, `PendSV` and `SysTick` are used
+/// by Hopter's scheduler and are rejected.
+///
+/// An exception handler function should satisfy the following signature
+/// requirements:
+/// - Has `extern "C"` ABI.
+/// - Is not `async`.
+/// - Is not variadic.
+/// - For `HardFault`, has one argument of type
+///   `&cortex_m_rt::ExceptionFrame` and returns `!`.
+/// - For other exceptions, has no argument and returns `()`.
+///
+/// Example:
+/// ```ignore
+/// #[exception(HardFault)]
+/// extern "C" fn hard_fault_handler(frame: &cortex_m_rt::ExceptionFrame) -> ! {
+///     /* report the faulting `frame.pc()` */
+/// }
+/// ```
+///
+/// The type of the exception frame is asserted in the expansion, which thus
+/// refers to the `cortex_m_rt` crate. Like [`#[main]`](main), the macro
+/// accepts `crate = path` to resolve it through a crate that re-exports it as
+/// `path::cortex_m_rt`, e.g. `#[exception(HardFault, crate = my_bsp::hopter)]`.
+///
+/// Exception handlers run without entering the Hopter kernel, so they must
+/// not call any Hopter API. The macro works by generating a trampoline
+/// function for the exception. For `HardFault`, the trampoline passes the
+/// exception frame stacked on the stack that was active when the fault
+/// occurred:
+///
+/// ```ignore
+/// #[unsafe(naked)]
+/// #[unsafe(export_name = "HardFault")]
+/// unsafe extern "C" fn __hardfault_entry() {
+///     ::core::arch::naked_asm!(
+///         "tst lr, #4",
+///         "ite eq",
+///         "mrseq r0, msp",
+///         "mrsne r0, psp",
+///         "b {handler_func}",
+///         handler_func = sym hard_fault_handler,
+///     )
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn exception(attr: TokenStream, item: TokenStream) -> TokenStream {
+    // Parse the `item` TokenStream into a Rust function.
+    let exception_func = parse_macro_input!(item as ItemFn);
+
+    // Parse the `attr` TokenStream into attribute arguments.
+    let attr_args = parse_macro_input!(attr as AttrArgs);
+
+    exception::expand(&attr_args.0, exception_func)
+}
+
+/// Mark a function as the entry function of a task, and generate a module
+/// named after the function to spawn it.
+///
+/// The function should satisfy the following signature requirements:
+/// - Has no argument, or one argument of a `Send + 'static` type.
+/// - Returns `()` or `!`.
+/// - Has the Rust ABI.
+/// - Is not generic.
+/// - Is not `async`.
+/// - Is not `unsafe`.
+/// - Is not variadic.
+///
+/// Example:
+/// ```ignore
+/// #[task]
+/// fn blinker(led: Led) {
+///     /* task logic */
+/// }
+///
+/// // In another task.
+/// blinker::spawn(led, 5, 2048).unwrap();
+/// ```
+///
+/// The generated `spawn` function takes the argument of the task, if any,
+/// followed by the priority and the initial stack size in bytes, and builds
+/// the task with `hopter::task::build()`. Like [`#[main]`](main), the task
+/// is started through a trampoline that receives the boxed argument as an
+/// `AtomicPtr<u8>`, so the argument type is checked when spawning rather
+/// than cast blindly. The macro accepts `crate = path` to resolve Hopter and
+/// `alloc` through a crate that re-exports them.
+///
+/// A task without argument can instead be spawned by the kernel at boot,
+/// after the main function runs, with `autostart` together with its
+/// priority and initial stack size in bytes. The macro places an entry for
+/// the task into the `.hopter_static_tasks` link section, which the kernel
+/// walks, and generates no `spawn` function:
+///
+/// ```ignore
+/// #[task(priority = 4, stack = 2048, autostart)]
+/// fn logger() -> ! {
+///     /* task logic */
+/// }
+/// ```
+///
+/// Task names must be unique across the program, which is checked when
+/// linking through a `__hopter_task_<name>` symbol exported for each task.
+///
+/// The `blinker` example expands to the following:
+///
+/// ```ignore
+/// extern "C-unwind" fn __blinker_trampoline(arg: ::core::sync::atomic::AtomicPtr<u8>) {
+///     let arg = arg.load(::core::sync::atomic::Ordering::SeqCst) as *mut Led;
+///     let arg = unsafe { ::alloc::boxed::Box::from_raw(arg) };
+///     blinker(*arg)
+/// }
+///
+/// mod blinker {
+///     use super::*;
+///
+///     pub fn spawn(
+///         arg: Led,
+///         priority: u8,
+///         stack: usize,
+///     ) -> ::core::result::Result<(), ::hopter::task::TaskBuildError> {
+///         let arg_ptr =
+///             ::alloc::boxed::Box::into_raw(::alloc::boxed::Box::new(arg)) as *mut u8;
+///         let arg = ::core::sync::atomic::AtomicPtr::new(arg_ptr);
+///         let result = ::hopter::task::build()
+///             .set_entry(move || super::__blinker_trampoline(arg))
+///             .set_priority(priority)
+///             .set_stack_init_size(stack)
+///             .spawn();
+///         if result.is_err() {
+///             ::core::mem::drop(unsafe { ::alloc::boxed::Box::from_raw(arg_ptr as *mut Led) });
+///         }
+///         result
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn task(attr: TokenStream, item: TokenStream) -> TokenStream {
+    // Parse the `item` TokenStream into a Rust function.
+    let task_func = parse_macro_input!(item as ItemFn);
+
+    // Parse the `attr` TokenStream into attribute arguments.
+    let attr_args = parse_macro_input!(attr as AttrArgs);
+
+    task::expand(&attr_args.0, task_func)
+}
+
 macro_rules! hander_macro_arg_error {
     () => {
         "Handler's argument must be one of the supported IRQs."
     };
 }
 
+macro_rules! hander_macro_param_error {
+    () => {
+        "Handler function can only have one parameter of type `u16`, which receives the IRQ number."
+    };
+}
+
+macro_rules! hander_macro_abi_error {
+    () => {
+        "Handler function must be `extern \"C\"` or `extern \"C-unwind\"`."
+    };
+}
+
 macro_rules! hander_macro_retval_error {
     () => {
         "Handler's return type must be ()."
     };
 }
 
+macro_rules! main_macro_arg_error {
+    () => {
+        "Main's argument must be one of `stack_size = N`, `priority = N`, \
+        `heap = N`, `device = path`, `board = path`, `report = path`, \
+        `on_error = policy` or `crate = path`."
+    };
+}
+
+macro_rules! main_macro_retval_error {
+    () => {
+        "Main function's return type must be (), ! or Result<(), E>."
+    };
+}
+
+/// Collects the errors found while validating an item, so that all of them
+/// are reported together rather than only the first one.
+#[derive(Default)]
+struct Errors(Option<Error>);
+
+impl Errors {
+    /// Record an error pointing at the given tokens.
+    fn push<T: ToTokens, U: Display>(&mut self, tokens: T, message: U) {
+        self.combine(Error::new_spanned(tokens, message));
+    }
+
+    /// Record an already constructed error.
+    fn combine(&mut self, error: Error) {
+        match &mut self.0 {
+            Some(errors) => errors.combine(error),
+            None => self.0 = Some(error),
+        }
+    }
+
+    /// Return all recorded errors as one combined error, if any.
+    fn finish(self) -> Result<()> {
+        match self.0 {
+            Some(errors) => Err(errors),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Return whether the type is written as `u16`.
+fn is_u16(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("u16"))
+}
+
+/// Emit the compile errors together with the unmodified user function, so
+/// that rustc does not additionally complain about the function missing.
+fn error_with_item(error: Error, item: &ItemFn) -> TokenStream {
+    let error = error.into_compile_error();
+    quote! {
+        #error
+        #item
+    }
+    .into()
+}
+
+/// Attributes of the user function that are copied onto the generated items,
+/// so that these are compiled under the same conditions and placed alongside
+/// the user function.
+#[derive(Default)]
+struct ForwardedAttrs {
+    /// The `#[cfg]` attributes, for every generated item.
+    cfg: TokenStream2,
+    /// The `#[cfg]`, `#[cfg_attr]`, `#[doc]` and `#[link_section]`
+    /// attributes, for generated functions.
+    func: TokenStream2,
+    /// The section named by `#[link_section]`, for entries defined in
+    /// assembly.
+    link_section: Option<String>,
+}
+
+impl ForwardedAttrs {
+    fn new(item: &ItemFn) -> Self {
+        let mut forwarded = ForwardedAttrs::default();
+
+        for attr in item.attrs.iter() {
+            if attr.path.is_ident("cfg") {
+                attr.to_tokens(&mut forwarded.cfg);
+                attr.to_tokens(&mut forwarded.func);
+            } else if attr.path.is_ident("cfg_attr") || attr.path.is_ident("doc") {
+                attr.to_tokens(&mut forwarded.func);
+            } else if let Some(section) = link_section_value(attr) {
+                attr.to_tokens(&mut forwarded.func);
+                forwarded.link_section = Some(section.value());
+            }
+        }
+
+        forwarded
+    }
+}
+
+/// Return the section named by a `#[link_section = ".name"]` attribute, which
+/// the macros themselves may write as `#[unsafe(link_section = ".name")]`.
+fn link_section_value(attr: &Attribute) -> Option<LitStr> {
+    let meta = if attr.path.is_ident("unsafe") {
+        attr.parse_args::<Meta>().ok()?
+    } else {
+        attr.parse_meta().ok()?
+    };
+
+    match meta {
+        Meta::NameValue(MetaNameValue {
+            path,
+            lit: Lit::Str(section),
+            ..
+        }) if path.is_ident("link_section") => Some(section),
+        _ => None,
+    }
+}
+
 /// The main function should satisfy the following signature requirements:
-/// - Has one and only one argument of type `cortex_m::Peripherals`.
-/// - Returns `()` or `!`.
+/// - Has no argument, or one argument of type `cortex_m::Peripherals`,
+///   optionally followed by one argument of the `Peripherals` type of a
+///   device crate.
+/// - Returns `()`, `!` or `Result<(), E>`.
 /// - Is not `async`.
 /// - Is not `unsafe`.
 /// - Is not variadic.
-fn check_main_function_signature(sig: &Signature) {
-    if sig.inputs.iter().count() != 1 {
-        panic!("Main function must receive one argument of type `cortex_m::Peripherals`.");
+fn check_main_function_signature(sig: &Signature) -> Result<()> {
+    let mut errors = Errors::default();
+
+    // The types are only checked syntactically here. Whether the paths name
+    // the expected peripherals is asserted in the expansion.
+    let expected = [
+        "Main function's argument must be of type `cortex_m::Peripherals`.",
+        "Main function's second argument must be the `Peripherals` type of a \
+        device crate.",
+    ];
+    for (input, expected) in sig.inputs.iter().zip(expected) {
+        match input {
+            FnArg::Receiver(receiver) => {
+                errors.push(receiver, "Main function cannot take `self`.");
+            }
+            FnArg::Typed(arg) if !matches!(&*arg.ty, Type::Path(_)) => {
+                errors.push(&arg.ty, expected);
+            }
+            _ => {}
+        }
+    }
+
+    for extra in sig.inputs.iter().skip(2) {
+        errors.push(
+            extra,
+            "Main function can only receive the core peripherals and the \
+            device peripherals.",
+        );
     }
 
     match &sig.output {
         // No return type specification.
         ReturnType::Default => {}
-        // Specified return type as `-> ()`.
+        // Specified return type as `-> ()` or `-> !`.
         ReturnType::Type(_, b) => match &**b {
-            Type::Tuple(t) => {
-                if t.elems.len() != 0 {
-                    panic!(hander_macro_retval_error!());
-                }
-            }
+            Type::Tuple(t) if t.elems.is_empty() => {}
             Type::Never(_) => {}
-            _ => panic!(hander_macro_retval_error!()),
+            // The `Ok` type and the error type are checked in the expansion.
+            _ if returns_result(&sig.output) => {}
+            _ => errors.push(b, main_macro_retval_error!()),
         },
     }
 
-    if sig.asyncness.is_some() {
-        panic!("Main function cannot be `async`.");
+    if let Some(asyncness) = &sig.asyncness {
+        errors.push(asyncness, "Main function cannot be `async`.");
     }
 
-    if sig.unsafety.is_some() {
-        panic!("Main function must be safe.");
+    if let Some(unsafety) = &sig.unsafety {
+        errors.push(unsafety, "Main function must be safe.");
     }
 
-    if sig.variadic.is_some() {
-        panic!("Handler function cannot be variadic.");
+    if let Some(variadic) = &sig.variadic {
+        errors.push(variadic, "Main function cannot be variadic.");
     }
+
+    errors.finish()
 }
 
 /// A handler function should satisfy the following signature requirements:
-/// - Has no argument.
+/// - Has no argument, or one argument of type `u16` receiving the IRQ number.
 /// - Returns `()`.
-/// - Has `extern "C"` ABI.
+/// - Has `extern "C"` or `extern "C-unwind"` ABI.
 /// - Is not `async`.
 /// - Is not variadic.
-fn check_handler_function_signature(sig: &Signature) {
-    if sig.inputs.iter().count() != 0 {
-        panic!("Handler function should not have any parameter.");
+fn check_handler_function_signature(sig: &Signature) -> Result<()> {
+    let mut errors = Errors::default();
+
+    // The only parameter allowed is the number of the triggering IRQ.
+    match sig.inputs.first() {
+        None => {}
+        Some(FnArg::Typed(arg)) if is_u16(&arg.ty) => {}
+        Some(arg) => errors.push(arg, hander_macro_param_error!()),
+    }
+
+    for extra in sig.inputs.iter().skip(1) {
+        errors.push(extra, hander_macro_param_error!());
     }
 
     match &sig.output {
@@ -217,144 +1264,735 @@ fn check_handler_function_signature(sig: &Signature) {
         ReturnType::Default => {}
         // Specified return type as `-> ()`.
         ReturnType::Type(_, b) => match &**b {
-            Type::Tuple(t) => {
-                if t.elems.len() != 0 {
-                    panic!(hander_macro_retval_error!());
+            Type::Tuple(t) if t.elems.is_empty() => {}
+            _ => errors.push(b, hander_macro_retval_error!()),
+        },
+    }
+
+    match sig.abi.as_ref() {
+        // Point at the `fn` keyword where the ABI is expected to be.
+        None => errors.push(sig.fn_token, hander_macro_abi_error!()),
+        // Point at the bare `extern` keyword.
+        Some(Abi { name: None, .. }) => errors.push(sig.abi.as_ref(), hander_macro_abi_error!()),
+        // Point at the ABI string.
+        Some(Abi {
+            name: Some(name), ..
+        }) => {
+            if name.value() != "C" && name.value() != "C-unwind" {
+                errors.push(name, hander_macro_abi_error!());
+            }
+        }
+    }
+
+    if let Some(asyncness) = &sig.asyncness {
+        errors.push(asyncness, "Handler function cannot be `async`.");
+    }
+
+    if let Some(variadic) = &sig.variadic {
+        errors.push(variadic, "Handler function cannot be variadic.");
+    }
+
+    errors.finish()
+}
+
+/// The BASEPRI value Hopter raises to in its critical sections. Handlers
+/// entering the kernel through `fast_irq_entry` must be masked by it, i.e.
+/// the value of their NVIC priority register must not be lower.
+const KERNEL_BASEPRI: u8 = 0x80;
+
+/// The interrupt entry flavors accepted by `entry = name`, given as the
+/// name and the path of the entry routine within `hopter::interrupt`.
+const ENTRY_FLAVORS: [(&str, &str); 3] = [
+    ("default", "default::fast_irq_entry"),
+    ("full_context", "full_context::irq_entry"),
+    ("irq_stack", "irq_stack::irq_entry"),
+];
+
+/// The parsed arguments of the handler attribute.
+struct HandlerArgs {
+    /// The IRQs the handler is bound to. EXTI lines sharing a vector are
+    /// listed with the NVIC number of the shared vector.
+    irqs: Vec<Irq>,
+    /// The EXTI lines among `irqs` that share a vector.
+    exti_lines: Vec<ExtiLine>,
+    /// The value of the NVIC priority register, lower being more urgent.
+    priority: Option<u8>,
+    /// Whether the kernel should unmask the IRQ at boot.
+    unmask: bool,
+    /// Whether the handler is exported directly as the vector, bypassing
+    /// `fast_irq_entry`.
+    raw: bool,
+    /// The link section to place the handler and its trampolines into.
+    section: Option<LitStr>,
+    /// The routine through which the trampolines enter the kernel.
+    entry: Path,
+    /// What to do when the handler panics.
+    on_panic: Option<PanicPolicy>,
+    /// Paths to the runtime crates used by the generated code.
+    crates: CratePaths,
+}
+
+/// The handler attribute should contain one or more distinct IRQ names of the
+/// selected chip, optionally followed by the `priority = N`, `unmask`, `raw`,
+/// `entry = flavor`, `on_panic = policy`, `section = ".name"` and
+/// `crate = path` options.
+fn parse_handler_args(attr_args: &[AttrArg], chip: &Chip) -> Result<HandlerArgs> {
+    let mut errors = Errors::default();
+    let mut irqs: Vec<Irq> = Vec::new();
+    // The argument naming each IRQ in `irqs`, to report errors at.
+    let mut irq_paths: Vec<&Path> = Vec::new();
+    let mut exti_lines: Vec<ExtiLine> = Vec::new();
+    let mut irq_seen = false;
+    let mut priority = None;
+    let mut unmask: Option<&Path> = None;
+    let mut raw = false;
+    let mut section = None;
+    let mut entry: Option<Path> = None;
+    let mut on_panic = None;
+    let mut krate = None;
+
+    for arg in attr_args {
+        match arg {
+            AttrArg::Path(path) if path.is_ident("unmask") => {
+                if unmask.is_some() {
+                    errors.push(path, "Duplicated `unmask` option.");
                 }
+                unmask = Some(path);
             }
-            _ => panic!(hander_macro_retval_error!()),
+            AttrArg::Path(path) if path.is_ident("raw") => {
+                if raw {
+                    errors.push(path, "Duplicated `raw` option.");
+                }
+                raw = true;
+            }
+            AttrArg::NameValue { name, value, .. } if name == "priority" => {
+                if priority.is_some() {
+                    errors.push(name, "Duplicated `priority` option.");
+                }
+                match parse_irq_priority(value, chip) {
+                    Ok(level) => priority = Some((value, level)),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "entry" => {
+                if entry.is_some() {
+                    errors.push(name, "Duplicated `entry` option.");
+                }
+                match args::expr_to_path(value).and_then(check_entry) {
+                    Ok(path) => entry = Some(path),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "on_panic" => {
+                if on_panic.is_some() {
+                    errors.push(name, "Duplicated `on_panic` option.");
+                }
+                match panic_policy::parse_panic_policy(value) {
+                    Ok(policy) => on_panic = Some((name, policy)),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "section" => {
+                if section.is_some() {
+                    errors.push(name, "Duplicated `section` option.");
+                }
+                match parse_link_section(value) {
+                    Ok(value) => section = Some(value),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "crate" => {
+                if krate.is_some() {
+                    errors.push(name, "Duplicated `crate` option.");
+                }
+                match args::expr_to_path(value) {
+                    Ok(path) => krate = Some(path),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            // Any other bare path names an IRQ.
+            AttrArg::Path(ss) => {
+                irq_seen = true;
+
+                // Convert the argument into a string and look it up.
+                let arg = quote! { #ss }.to_string();
+
+                // Verify that the string names one of the IRQs of the chip,
+                // or an EXTI line served by a shared vector.
+                if irqs.iter().any(|irq| irq.name == arg) {
+                    errors.push(ss, format!("IRQ `{}` is listed more than once.", arg));
+                    continue;
+                }
+
+                match (chip.find_irq(&arg), chip.find_exti_line(&arg)) {
+                    (Some(irq), _) => {
+                        irqs.push(irq.clone());
+                        irq_paths.push(ss);
+                    }
+                    (None, Some(line)) => {
+                        irqs.push(Irq {
+                            name: arg.into(),
+                            number: line.vector.number,
+                        });
+                        irq_paths.push(ss);
+                        exti_lines.push(line);
+                    }
+                    (None, None) => errors.push(
+                        ss,
+                        format!(
+                            "`{}` is not an IRQ of the selected chip `{}`.",
+                            arg, chip.name
+                        ),
+                    ),
+                }
+            }
+            _ => errors.push(arg, hander_macro_arg_error!()),
+        }
+    }
+
+    if !irq_seen {
+        errors.combine(Error::new(Span::call_site(), hander_macro_arg_error!()));
+    }
+
+    // Only handlers entering the kernel are limited by its BASEPRI level,
+    // which is compared in hardware terms, as the number of priority bits
+    // decides which level it stands for.
+    let priority = priority.map(|(expr, level)| (expr, level, nvic_priority(level, chip)));
+    if let (Some((expr, level, value)), false) = (priority, raw) {
+        if value < KERNEL_BASEPRI {
+            let step = 1u16 << (8 - chip.nvic_prio_bits);
+            errors.push(
+                expr,
+                format!(
+                    "IRQ priority {} is more urgent than {}, the most urgent \
+                    priority at which handlers can enter the Hopter kernel. \
+                    Use `raw` for handlers that never call Hopter.",
+                    level,
+                    u16::from(KERNEL_BASEPRI).div_ceil(step)
+                ),
+            );
+        }
+    }
+
+    // A raw handler is the vector itself, and so can serve only one vector
+    // of its own.
+    if raw {
+        for path in irq_paths.iter().skip(1) {
+            errors.push(path, "Raw handler must be bound to exactly one IRQ.");
+        }
+        for line in exti_lines.iter() {
+            errors.push(
+                irq_path(&irqs, &irq_paths, &format!("EXTI{}", line.line)),
+                format!(
+                    "`EXTI{}` is dispatched through the kernel and cannot have a raw handler.",
+                    line.line
+                ),
+            );
+        }
+    }
+
+    // EXTI lines enter the kernel through the dispatcher of their vector,
+    // which is shared with other handlers.
+    if let (Some(entry), false) = (&entry, exti_lines.is_empty()) {
+        if !entry.is_ident("default") {
+            errors.push(
+                entry,
+                "EXTI lines are dispatched through the `default` entry of their shared vector.",
+            );
+        }
+    }
+
+    // The NVIC only knows the shared vector, whose configuration would be
+    // fought over by the handlers of its lines.
+    if let Some(line) = exti_lines.first() {
+        let shared_error = format!(
+            "EXTI lines share the `{}` vector, which must be configured through \
+            the NVIC directly.",
+            line.vector.name
+        );
+        if let Some((expr, _, _)) = &priority {
+            errors.push(expr, &shared_error);
+        }
+        if let Some(path) = unmask {
+            errors.push(path, &shared_error);
+        }
+    }
+
+    if let (Some((name, _)), true) = (&on_panic, raw) {
+        errors.push(name, "Raw handler cannot have a panic policy.");
+    }
+
+    if let (Some(entry), true) = (&entry, raw) {
+        errors.push(
+            entry,
+            "Raw handler does not enter the kernel, so it takes no `entry`.",
+        );
+    }
+
+    // A shared vector bound directly would replace its dispatcher.
+    for line in exti_lines.iter() {
+        if irqs.iter().any(|irq| irq.name == line.vector.name) {
+            errors.push(
+                irq_path(&irqs, &irq_paths, &line.vector.name),
+                format!(
+                    "`EXTI{}` cannot be bound together with its shared vector `{}`.",
+                    line.line, line.vector.name
+                ),
+            );
+        }
+    }
+
+    let crates = CratePaths::new(krate.as_ref());
+    let entry = resolve_entry(entry.as_ref(), &crates.hopter);
+
+    errors.finish().map(|_| HandlerArgs {
+        irqs,
+        exti_lines,
+        priority: priority.map(|(_, _, value)| value),
+        unmask: unmask.is_some(),
+        raw,
+        section,
+        entry,
+        on_panic: on_panic.map(|(_, policy)| policy),
+        crates,
+    })
+}
+
+/// Return the argument that named the IRQ called `name` among `irqs`.
+fn irq_path<'a>(irqs: &[Irq], irq_paths: &[&'a Path], name: &str) -> &'a Path {
+    let index = irqs.iter().position(|irq| irq.name == name).unwrap();
+    irq_paths[index]
+}
+
+/// An entry given by a single name must be one of the known flavors. Longer
+/// paths name a custom entry routine.
+fn check_entry(path: Path) -> Result<Path> {
+    let name = match path.get_ident() {
+        Some(name) => name.to_string(),
+        None => return Ok(path),
+    };
+
+    if ENTRY_FLAVORS.iter().any(|(flavor, _)| *flavor == name) {
+        return Ok(path);
+    }
+
+    let flavors: Vec<String> = ENTRY_FLAVORS
+        .iter()
+        .map(|(flavor, _)| format!("`{}`", flavor))
+        .collect();
+
+    Err(Error::new_spanned(
+        path,
+        format!(
+            "Unknown entry `{}`. Expected one of {}, or a path to an entry routine.",
+            name,
+            flavors.join(", ")
+        ),
+    ))
+}
+
+/// Resolve the entry option into the path of the entry routine, which is
+/// `fast_irq_entry` of the default flavor if no entry is given.
+fn resolve_entry(entry: Option<&Path>, hopter: &Path) -> Path {
+    let name = match entry {
+        Some(path) => match path.get_ident() {
+            Some(name) => name.to_string(),
+            None => return path.clone(),
         },
+        None => "default".to_string(),
+    };
+
+    let routine = ENTRY_FLAVORS
+        .iter()
+        .find(|(flavor, _)| *flavor == name)
+        .map(|(_, routine)| *routine)
+        .unwrap_or(ENTRY_FLAVORS[0].1);
+    let routine: Path = syn::parse_str(routine).unwrap();
+
+    parse_quote!(#hopter::interrupt::#routine)
+}
+
+/// Generate a compile-time assertion that `ty`, the type of what `subject`
+/// describes, is `expected`. The error points at `ty` if it is not.
+fn generate_type_assertion<T: ToTokens>(
+    ty: &T,
+    subject: &str,
+    expected: &TokenStream2,
+    cfg_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let span = ty.span();
+
+    let diagnostic = if cfg!(hopter_diagnostic) {
+        // Show `::cortex_m::Peripherals` as `cortex_m::Peripherals`.
+        let expected_name = expected.to_string().replace(' ', "");
+        let expected_name = expected_name.trim_start_matches("::");
+        let message = format!("{} must be of type `{}`", subject, expected_name);
+        let label = format!("expected `{}`", expected_name);
+        Some(quote! {
+            #[diagnostic::on_unimplemented(message = #message, label = #label)]
+        })
+    } else {
+        None
+    };
+
+    quote_spanned! {span=>
+        #cfg_attrs
+        const _: fn() = || {
+            #diagnostic
+            trait Expected {}
+            impl Expected for #expected {}
+            fn assert_expected<T: Expected>() {}
+            assert_expected::<#ty>();
+        };
     }
+}
 
-    let abi = sig
-        .abi
-        .as_ref()
-        .unwrap_or_else(|| panic!("Handler function must be `extern \"C\"`."));
+/// Whether the return type is a path to a `Result` type.
+fn returns_result(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Result"),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    }
+}
 
-    let abi_name = abi
-        .name
-        .as_ref()
-        .unwrap_or_else(|| panic!("Handler function must be `extern \"C\"`."))
-        .value();
+/// Expand an optional setting to an `Option` expression.
+fn option_tokens<T: ToTokens>(value: Option<T>) -> TokenStream2 {
+    match value {
+        Some(value) => quote! { ::core::option::Option::Some(#value) },
+        None => quote! { ::core::option::Option::None },
+    }
+}
+
+/// Smallest and largest accepted heap size.
+const HEAP_SIZE_RANGE: core::ops::RangeInclusive<usize> = (1 << 10)..=(16 << 20);
+
+/// The parsed arguments of the main attribute.
+struct MainArgs {
+    /// The initial stack size in bytes of the main task.
+    stack_size: Option<usize>,
+    /// The priority of the main task.
+    priority: Option<u8>,
+    /// The size in bytes of the heap.
+    heap: Option<usize>,
+    /// The type of the device peripherals.
+    device: Option<Path>,
+    /// The board type initialized from the peripherals.
+    board: Option<Path>,
+    /// The hook reporting an error returned by the main function.
+    report: Option<Path>,
+    /// What to do after the main function returned an error.
+    on_error: Option<ExitPolicy>,
+    /// Paths to the runtime crates used by the generated code.
+    crates: CratePaths,
+}
+
+/// The main attribute accepts the optional `stack_size = N`, `priority = N`,
+/// `heap = N`, `device = path`, `board = path`, `report = path`,
+/// `on_error = policy` and `crate = path` options.
+fn parse_main_args(attr_args: &[AttrArg]) -> Result<MainArgs> {
+    let mut errors = Errors::default();
+    let mut stack_size = None;
+    let mut priority = None;
+    let mut heap = None;
+    let mut device: Option<Path> = None;
+    let mut board: Option<Path> = None;
+    let mut report: Option<Path> = None;
+    let mut on_error = None;
+    let mut krate: Option<Path> = None;
+
+    for arg in attr_args {
+        match arg {
+            AttrArg::NameValue { name, value, .. } if name == "stack_size" => {
+                if stack_size.is_some() {
+                    errors.push(name, "Duplicated `stack_size` option.");
+                }
+                match args::parse_size(value, "Main task stack size", task::TASK_STACK_RANGE) {
+                    Ok(value) => stack_size = Some(value),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "priority" => {
+                if priority.is_some() {
+                    errors.push(name, "Duplicated `priority` option.");
+                }
+                match task::parse_task_priority(value) {
+                    Ok(value) => priority = Some(value),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "heap" => {
+                if heap.is_some() {
+                    errors.push(name, "Duplicated `heap` option.");
+                }
+                match args::parse_size(value, "Heap size", HEAP_SIZE_RANGE) {
+                    Ok(value) => heap = Some(value),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "device" => {
+                if device.is_some() {
+                    errors.push(name, "Duplicated `device` option.");
+                }
+                match args::expr_to_path(value) {
+                    Ok(path) => device = Some(path),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "board" => {
+                if board.is_some() {
+                    errors.push(name, "Duplicated `board` option.");
+                }
+                match args::expr_to_path(value) {
+                    Ok(path) => board = Some(path),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "report" => {
+                if report.is_some() {
+                    errors.push(name, "Duplicated `report` option.");
+                }
+                match args::expr_to_path(value) {
+                    Ok(path) => report = Some(path),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "on_error" => {
+                if on_error.is_some() {
+                    errors.push(name, "Duplicated `on_error` option.");
+                }
+                match exit_policy::parse_exit_policy(value) {
+                    Ok(policy) => on_error = Some(policy),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            AttrArg::NameValue { name, value, .. } if name == "crate" => {
+                if krate.is_some() {
+                    errors.push(name, "Duplicated `crate` option.");
+                }
+                match args::expr_to_path(value) {
+                    Ok(path) => krate = Some(path),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            _ => errors.push(arg, main_macro_arg_error!()),
+        }
+    }
 
-    if abi_name != "C" {
-        panic!("Handler function must be `extern \"C\"`.");
+    errors.finish().map(|_| MainArgs {
+        stack_size,
+        priority,
+        heap,
+        device,
+        board,
+        report,
+        on_error,
+        crates: CratePaths::new(krate.as_ref()),
+    })
+}
+
+/// Validate an IRQ priority level. Levels range from 0, the most urgent, to
+/// one less than the number of levels the NVIC implements.
+fn parse_irq_priority(lit: &Expr, chip: &Chip) -> Result<u8> {
+    let levels = 1u16 << chip.nvic_prio_bits;
+
+    let value = match lit {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse::<u16>()?,
+        _ => return Err(Error::new_spanned(lit, "IRQ priority must be an integer.")),
+    };
+
+    match u8::try_from(value) {
+        Ok(value) if u16::from(value) < levels => Ok(value),
+        _ => Err(Error::new_spanned(
+            lit,
+            format!("IRQ priority must be in the range 0..={}.", levels - 1),
+        )),
     }
+}
+
+/// Validate the name of a link section, such as `.ramfunc`.
+fn parse_link_section(value: &Expr) -> Result<LitStr> {
+    let section = match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(section),
+            ..
+        }) => section,
+        _ => return Err(Error::new_spanned(value, "Section must be a string.")),
+    };
+
+    let name = section.value();
+    let valid = name.len() > 1
+        && name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
 
-    if sig.asyncness.is_some() {
-        panic!("Handler function cannot be `async`.");
+    if !valid {
+        return Err(Error::new_spanned(
+            section,
+            "Section must be a name such as `.ramfunc`, made of letters, \
+            digits, `_` and `.`.",
+        ));
     }
 
-    if sig.variadic.is_some() {
-        panic!("Handler function cannot be variadic.");
+    Ok(section.clone())
+}
+
+/// Generate references to the symbols that the linker script defines at
+/// the start and the end of `section`, so that linking fails if the linker
+/// script does not provide the section. For `.ramfunc`, these are
+/// `__sramfunc` and `__eramfunc`. Dots within the name become `_`.
+fn generate_section_check(section: &LitStr, span: Span, cfg_attrs: &TokenStream2) -> TokenStream2 {
+    let name = section.value()[1..].replace('.', "_");
+    let start = format!("__s{}", name);
+    let end = format!("__e{}", name);
+    let extern_block = trampoline::extern_block(span);
+
+    quote_spanned! {span=>
+        #cfg_attrs
+        const _: () = {
+            #extern_block {
+                #[link_name = #start]
+                static SECTION_START: u8;
+                #[link_name = #end]
+                static SECTION_END: u8;
+            }
+
+            #[used]
+            static SECTION_CHECK: [&u8; 2] = unsafe { [&SECTION_START, &SECTION_END] };
+        };
     }
 }
 
-/// The handler attribute should contain one and only one argument, which is
-/// a supported IRQ name.
-fn parse_attribute_arg_to_irq(attr_args: &[NestedMeta]) -> String {
-    // Check that there is only one attribute argument.
-    if attr_args.len() != 1 {
-        panic!(hander_macro_arg_error!());
+/// Convert a priority level into the value of the NVIC priority register,
+/// of which only the upper bits are used. The level was checked to fit.
+fn nvic_priority(level: u8, chip: &Chip) -> u8 {
+    u8::try_from(u16::from(level) << (8 - chip.nvic_prio_bits))
+        .expect("priority level fits the priority bits")
+}
+
+/// Generate a module named after the handler function that exposes the IRQs
+/// the handler is bound to, e.g. `tim7_handler::IRQ_NUMBER`. Modules and
+/// functions live in different namespaces, so the names do not clash.
+///
+/// If a priority or `unmask` is requested, the module also places a record
+/// per IRQ into the `.hopter_irq_config` link section, which the kernel walks
+/// at boot to program the NVIC.
+fn generate_irq_module(
+    handler_func: &ItemFn,
+    args: &HandlerArgs,
+    cfg_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let vis = &handler_func.vis;
+    let func_name = &handler_func.sig.ident;
+    let irq_count = args.irqs.len();
+    let irq_numbers: Vec<u16> = args.irqs.iter().map(|irq| irq.number).collect();
+    let CratePaths {
+        hopter, cortex_m, ..
+    } = &args.crates;
+
+    let module_doc = format!("The IRQs that [`{}`] is bound to.", func_name);
+
+    // Name each IRQ individually, as well as `IRQ` when there is only one.
+    let mut named_irqs = TokenStream2::new();
+
+    for irq in args.irqs.iter() {
+        let name = Ident::new(&irq.name, Span::call_site());
+        let number = irq.number;
+        let doc = format!(
+            "The `{}` IRQ, usable with the `cortex_m::peripheral::NVIC` API.",
+            irq.name
+        );
+        named_irqs.extend(quote! {
+            #[doc = #doc]
+            pub const #name: Irq = Irq(#number);
+        });
+    }
+
+    if let [irq] = args.irqs.as_slice() {
+        let number = irq.number;
+        let number_doc = format!("NVIC number of the `{}` IRQ.", irq.name);
+        let irq_doc = format!(
+            "The `{}` IRQ, usable with the `cortex_m::peripheral::NVIC` API.",
+            irq.name
+        );
+        named_irqs.extend(quote! {
+            #[doc = #number_doc]
+            pub const IRQ_NUMBER: u16 = #number;
+
+            #[doc = #irq_doc]
+            pub const IRQ: Irq = Irq(IRQ_NUMBER);
+        });
     }
 
-    // Convert the argument into a string.
-    let arg = match attr_args.first().unwrap() {
-        NestedMeta::Meta(Meta::Path(ss)) => quote! { #ss }.to_string(),
-        _ => panic!(hander_macro_arg_error!()),
+    let boot_config = if args.priority.is_some() || args.unmask {
+        let priority = match args.priority {
+            Some(priority) => quote! { ::core::option::Option::Some(#priority) },
+            None => quote! { ::core::option::Option::None },
+        };
+        let unmask = args.unmask;
+
+        let link_section = trampoline::unsafe_attr(
+            Span::call_site(),
+            quote! { link_section = ".hopter_irq_config" },
+        );
+
+        Some(quote! {
+            #[used]
+            #link_section
+            static BOOT_CONFIG: [#hopter::interrupt::IrqConfig; #irq_count] = [
+                #(
+                    #hopter::interrupt::IrqConfig {
+                        irq: #irq_numbers,
+                        priority: #priority,
+                        unmask: #unmask,
+                    }
+                ),*
+            ];
+        })
+    } else {
+        None
     };
 
-    // Verify that the string names one of the supported IRQs.
-    if !SUPPORTED_IRQS.iter().any(|irq| irq == &arg) {
-        panic!(hander_macro_arg_error!());
-    }
-
-    arg
-}
-
-/// List of supported IRQ names.
-const SUPPORTED_IRQS: [&str; 83] = [
-    "WWDG",
-    "PVD",
-    "TAMP_STAMP",
-    "RTC_WKUP",
-    "RCC",
-    "EXTI0",
-    "EXTI1",
-    "EXTI2",
-    "EXTI3",
-    "EXTI4",
-    "DMA1_STREAM0",
-    "DMA1_STREAM1",
-    "DMA1_STREAM2",
-    "DMA1_STREAM3",
-    "DMA1_STREAM4",
-    "DMA1_STREAM5",
-    "DMA1_STREAM6",
-    "ADC",
-    "CAN1_TX",
-    "CAN1_RX0",
-    "CAN1_RX1",
-    "CAN1_SCE",
-    "EXTI9_5",
-    "TIM1_BRK_TIM9",
-    "TIM1_UP_TIM10",
-    "TIM1_TRG_COM_TIM11",
-    "TIM1_CC",
-    "TIM2",
-    "TIM3",
-    "TIM4",
-    "I2C1_EV",
-    "I2C1_ER",
-    "I2C2_EV",
-    "I2C2_ER",
-    "SPI1",
-    "SPI2",
-    "USART1",
-    "USART2",
-    "USART3",
-    "EXTI15_10",
-    "RTC_ALARM",
-    "OTG_FS_WKUP",
-    "TIM8_BRK_TIM12",
-    "TIM8_UP_TIM13",
-    "TIM8_TRG_COM_TIM14",
-    "TIM8_CC",
-    "DMA1_STREAM7",
-    "FSMC",
-    "SDIO",
-    "TIM5",
-    "SPI3",
-    "UART4",
-    "UART5",
-    "TIM6_DAC",
-    "TIM7",
-    "DMA2_STREAM0",
-    "DMA2_STREAM1",
-    "DMA2_STREAM2",
-    "DMA2_STREAM3",
-    "DMA2_STREAM4",
-    "ETH",
-    "ETH_WKUP",
-    "CAN2_TX",
-    "CAN2_RX0",
-    "CAN2_RX1",
-    "CAN2_SCE",
-    "OTG_FS",
-    "DMA2_STREAM5",
-    "DMA2_STREAM6",
-    "DMA2_STREAM7",
-    "USART6",
-    "I2C3_EV",
-    "I2C3_ER",
-    "OTG_HS_EP1_OUT",
-    "OTG_HS_EP1_IN",
-    "OTG_HS_WKUP",
-    "OTG_HS",
-    "DCMI",
-    "CRYP",
-    "HASH_RNG",
-    "FPU",
-    "LTDC",
-    "LTDC_ER",
-];
+    quote! {
+        #cfg_attrs
+        #[doc = #module_doc]
+        #[allow(non_upper_case_globals)]
+        #vis mod #func_name {
+            /// NVIC numbers of the IRQs, in the order they are listed.
+            pub const IRQ_NUMBERS: [u16; #irq_count] = [#(#irq_numbers),*];
+
+            /// The IRQs, in the order they are listed.
+            pub const IRQS: [Irq; #irq_count] = [#(Irq(#irq_numbers)),*];
+
+            #named_irqs
+
+            /// Typed IRQ number implementing `InterruptNumber`.
+            #[derive(
+                ::core::clone::Clone,
+                ::core::marker::Copy,
+                ::core::fmt::Debug,
+                ::core::cmp::PartialEq,
+                ::core::cmp::Eq,
+            )]
+            pub struct Irq(u16);
+
+            unsafe impl #cortex_m::interrupt::InterruptNumber for Irq {
+                #[inline]
+                fn number(self) -> u16 {
+                    self.0
+                }
+            }
+
+            #boot_config
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Panic policies of interrupt handlers, selected with the `on_panic`
+//! option of [`#[handler]`](crate::handler).
+//!
+//! The handler is called through a shim holding a guard, which is forgotten
+//! when the handler returns. If the handler panics, the guard is dropped
+//! while the panic unwinds through the shim and applies the policy. The
+//! panic then keeps unwinding into the kernel.
+
+use crate::{
+    args::{self, CratePaths},
+    exti,
+    irqs::{ExtiLine, Irq},
+};
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::{Error, Expr, Path, Result};
+
+/// What to do when a handler panics.
+pub(crate) enum PanicPolicy {
+    /// Only let the kernel recover, so that the handler runs again on the
+    /// next interrupt.
+    Restart,
+    /// Mask the IRQs the handler is bound to.
+    DisableIrq,
+    /// Reset the MCU.
+    Reset,
+    /// Call the given function with the number of the IRQ.
+    Hook(Path),
+}
+
+/// Parse `restart`, `disable_irq`, `reset`, or a path to a hook function.
+pub(crate) fn parse_panic_policy(value: &Expr) -> Result<PanicPolicy> {
+    let path = args::expr_to_path(value)?;
+
+    let name = match path.get_ident() {
+        Some(name) => name.to_string(),
+        None => return Ok(PanicPolicy::Hook(path)),
+    };
+
+    match name.as_str() {
+        "restart" => Ok(PanicPolicy::Restart),
+        "disable_irq" => Ok(PanicPolicy::DisableIrq),
+        "reset" => Ok(PanicPolicy::Reset),
+        _ => Err(Error::new_spanned(
+            path,
+            "Panic policy must be one of `restart`, `disable_irq`, `reset`, \
+            or a path to a function receiving the IRQ number.",
+        )),
+    }
+}
+
+/// Wrap `call`, which calls the handler function for `irq`, so that the
+/// `policy` is applied if the call panics. `exti_line` is the EXTI line if
+/// `irq` is one.
+pub(crate) fn guard_call(
+    call: TokenStream2,
+    policy: &PanicPolicy,
+    func_name: &Ident,
+    irq: &Irq,
+    exti_line: Option<&ExtiLine>,
+    crates: &CratePaths,
+) -> TokenStream2 {
+    let span = func_name.span();
+    let cortex_m = &crates.cortex_m;
+
+    let on_panic = match (policy, exti_line) {
+        (PanicPolicy::Restart, _) => return call,
+        // Masking the shared vector would silence the other lines as well.
+        (PanicPolicy::DisableIrq, Some(line)) => exti::mask_line(line),
+        (PanicPolicy::DisableIrq, None) => {
+            let irq_name = Ident::new(&irq.name, Span::call_site());
+            quote_spanned! {span=>
+                #cortex_m::peripheral::NVIC::mask(#func_name::#irq_name);
+            }
+        }
+        (PanicPolicy::Reset, _) => quote_spanned! {span=>
+            #cortex_m::peripheral::SCB::sys_reset();
+        },
+        (PanicPolicy::Hook(hook), _) => {
+            let irq_number = irq.number;
+            quote! { #hook(#irq_number); }
+        }
+    };
+
+    quote_spanned! {span=>
+        struct PanicGuard;
+
+        impl ::core::ops::Drop for PanicGuard {
+            fn drop(&mut self) {
+                #on_panic
+            }
+        }
+
+        let guard = PanicGuard;
+        #call;
+        ::core::mem::forget(guard);
+    }
+}
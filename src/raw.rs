@@ -0,0 +1,120 @@
+//! Checks for handlers bound with the `raw` option of
+//! [`#[handler]`](crate::handler).
+//!
+//! A raw handler is exported directly as the vector symbol and runs without
+//! the bookkeeping of `fast_irq_entry`, so it must not call any Hopter API.
+//! The handler acknowledges this by being declared `unsafe`, and the macro
+//! rejects any path into the Hopter crate within the handler body. Paths
+//! hidden inside macro invocations, or reached through other functions, are
+//! not detected.
+
+use crate::Errors;
+use syn::{
+    visit::{self, Visit},
+    Abi, ItemFn, ItemUse, Path, Result, UseTree,
+};
+
+/// Check the signature and the body of a raw handler. `hopter` is the path
+/// through which the Hopter crate is referred to.
+pub(crate) fn check_raw_handler(handler_func: &ItemFn, hopter: &Path) -> Result<()> {
+    let mut errors = Errors::default();
+    let sig = &handler_func.sig;
+
+    if sig.unsafety.is_none() {
+        errors.push(
+            sig.fn_token,
+            "Raw handler must be `unsafe extern \"C\"`, acknowledging that it \
+            must not call any Hopter API.",
+        );
+    }
+
+    // Nothing above the vector could catch a panic unwinding out of it.
+    if let Some(Abi {
+        name: Some(name), ..
+    }) = &sig.abi
+    {
+        if name.value() == "C-unwind" {
+            errors.push(
+                name,
+                "Raw handler must be `unsafe extern \"C\"`, as a panic cannot \
+                unwind out of the vector.",
+            );
+        }
+    }
+
+    for input in sig.inputs.iter() {
+        errors.push(input, "Raw handler should not have any parameter.");
+    }
+
+    let mut finder = HopterPathFinder {
+        hopter: hopter
+            .segments
+            .iter()
+            .map(|s| s.ident.to_string())
+            .collect(),
+        errors: &mut errors,
+    };
+    finder.visit_block(&handler_func.block);
+
+    errors.finish()
+}
+
+/// Reports every path that starts with the path of the Hopter crate.
+struct HopterPathFinder<'a> {
+    /// The segments of the path of the Hopter crate.
+    hopter: Vec<String>,
+    errors: &'a mut Errors,
+}
+
+impl HopterPathFinder<'_> {
+    fn report<T: quote::ToTokens>(&mut self, tokens: T) {
+        self.errors.push(
+            tokens,
+            "Raw handler cannot use Hopter, as it runs without entering the kernel.",
+        );
+    }
+}
+
+impl<'ast> Visit<'ast> for HopterPathFinder<'_> {
+    fn visit_path(&mut self, path: &'ast Path) {
+        let uses_hopter = path.segments.len() > self.hopter.len()
+            && path
+                .segments
+                .iter()
+                .zip(self.hopter.iter())
+                .all(|(segment, hopter)| segment.ident == hopter);
+
+        if uses_hopter {
+            self.report(path);
+        }
+
+        visit::visit_path(self, path);
+    }
+
+    fn visit_item_use(&mut self, item: &'ast ItemUse) {
+        if use_tree_reaches(&item.tree, &self.hopter) {
+            self.report(item);
+        }
+
+        visit::visit_item_use(self, item);
+    }
+}
+
+/// Whether the use tree imports anything from under the `hopter` path.
+fn use_tree_reaches(tree: &UseTree, hopter: &[String]) -> bool {
+    let (first, rest) = match hopter.split_first() {
+        Some(split) => split,
+        None => return true,
+    };
+
+    match tree {
+        UseTree::Path(path) => path.ident == first && use_tree_reaches(&path.tree, rest),
+        UseTree::Name(name) => name.ident == first && rest.is_empty(),
+        UseTree::Rename(rename) => rename.ident == first && rest.is_empty(),
+        UseTree::Group(group) => group
+            .items
+            .iter()
+            .any(|tree| use_tree_reaches(tree, hopter)),
+        UseTree::Glob(_) => false,
+    }
+}
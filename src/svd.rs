@@ -0,0 +1,394 @@
+//! Build the IRQ catalog from a CMSIS-SVD file, enabled by the `svd` feature.
+//!
+//! The SVD file is located through, in order of precedence:
+//! - The `HOPTER_SVD` environment variable.
+//! - A top-level `svd = "path/to/chip.svd"` entry in a `hopter.toml` file
+//!   placed next to the `Cargo.toml` of the crate using the macros.
+//!
+//! Relative paths are resolved against the directory containing the
+//! `Cargo.toml` of the crate using the macros.
+//!
+//! Only the `<interrupt>` elements and the `<nvicPrioBits>` of the SVD file
+//! are inspected. IRQ names are converted to upper case so that they match
+//! the naming of the built-in catalogs, e.g. `DMA1_Stream0` becomes
+//! `DMA1_STREAM0`.
+//!
+//! The SVD file is read once per crate, when the first `#[handler]` of the
+//! crate is expanded, as it may well be larger than a megabyte. Catalogs are
+//! cached per crate, since a single process may expand the macros of several
+//! crates, as a language server does.
+
+use crate::irqs::{Chip, Irq};
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs, mem,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+/// Name of the environment variable pointing to the SVD file.
+const SVD_ENV_VAR: &str = "HOPTER_SVD";
+
+/// Name of the configuration file that may point to the SVD file.
+const CONFIG_FILE: &str = "hopter.toml";
+
+/// Key of the catalog cache, made of `CARGO_MANIFEST_DIR` and `HOPTER_SVD`,
+/// which together decide the SVD file of a crate.
+type CacheKey = (Option<OsString>, Option<OsString>);
+
+/// The catalog read for a crate.
+struct CacheEntry {
+    chip: Result<Chip, String>,
+    /// Whether an expansion already made the crate depend on the files the
+    /// catalog was read from.
+    tracked: bool,
+}
+
+/// Return the chip catalog read from the SVD file of the current crate.
+pub(crate) fn load_chip() -> Result<Chip, String> {
+    with_cache_entry(|entry| entry.chip.clone())
+}
+
+/// Return whether the files the catalog of the current crate was read from
+/// still have to be tracked, and consider them tracked from now on.
+pub(crate) fn take_tracking() -> bool {
+    with_cache_entry(|entry| !mem::replace(&mut entry.tracked, true))
+}
+
+/// Call `f` with the cache entry of the current crate, reading the SVD file
+/// if the crate has no entry yet.
+fn with_cache_entry<R>(f: impl FnOnce(&mut CacheEntry) -> R) -> R {
+    static CACHE: Mutex<BTreeMap<CacheKey, CacheEntry>> = Mutex::new(BTreeMap::new());
+
+    let key = (env::var_os("CARGO_MANIFEST_DIR"), env::var_os(SVD_ENV_VAR));
+
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    let entry = cache.entry(key).or_insert_with(|| CacheEntry {
+        chip: read_chip(),
+        tracked: false,
+    });
+
+    f(entry)
+}
+
+/// Locate, read and parse the SVD file into a chip catalog.
+fn read_chip() -> Result<Chip, String> {
+    let (path, config_path) = locate_svd()?;
+
+    let svd = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read SVD file `{}`: {}", path.display(), e))?;
+
+    let svd = strip_comments(&svd);
+
+    // The first `<name>` of the file is the name of the `<device>`.
+    let name = element_text(&svd, "name")
+        .map(|name| name.to_lowercase())
+        .unwrap_or_else(|| path.display().to_string());
+
+    let irqs = parse_interrupts(&svd)
+        .map_err(|e| format!("Malformed SVD file `{}`: {}", path.display(), e))?;
+
+    // The `<cpu>` description is optional. Assume 4 bits like the built-in
+    // catalogs when it is missing.
+    let nvic_prio_bits = match element_text(&svd, "nvicPrioBits") {
+        Some(bits) => parse_number(bits)
+            .and_then(|bits| u8::try_from(bits).ok())
+            .filter(|bits| (1..=8).contains(bits))
+            .ok_or_else(|| {
+                format!(
+                    "Malformed SVD file `{}`: invalid `<nvicPrioBits>` `{}`",
+                    path.display(),
+                    bits
+                )
+            })?,
+        None => 4,
+    };
+
+    Ok(Chip {
+        name: Cow::Owned(name),
+        irqs: Cow::Owned(irqs),
+        nvic_prio_bits,
+        svd_path: Some(path),
+        config_path,
+    })
+}
+
+/// Find the SVD file from the environment variable or the config file, and
+/// return it together with the config file if that was used.
+fn locate_svd() -> Result<(PathBuf, Option<PathBuf>), String> {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .map_err(|_| "`CARGO_MANIFEST_DIR` is not set.".to_string())?;
+
+    if let Ok(path) = env::var(SVD_ENV_VAR) {
+        return Ok((manifest_dir.join(path), None));
+    }
+
+    let config_path = manifest_dir.join(CONFIG_FILE);
+
+    let config = fs::read_to_string(&config_path).map_err(|_| {
+        format!(
+            "The `svd` feature is enabled, but neither is `{}` set nor does `{}` exist.",
+            SVD_ENV_VAR,
+            config_path.display()
+        )
+    })?;
+
+    match config_svd_entry(&config) {
+        Some(path) => Ok((manifest_dir.join(Path::new(&path)), Some(config_path))),
+        None => Err(format!(
+            "`{}` does not contain a top-level `svd = \"...\"` entry.",
+            config_path.display()
+        )),
+    }
+}
+
+/// Extract the value of the top-level `svd` key from `hopter.toml`.
+fn config_svd_entry(config: &str) -> Option<String> {
+    for line in config.lines() {
+        let line = line.trim();
+
+        // Keys after a table header no longer belong to the top level.
+        if line.starts_with('[') {
+            break;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        if key.trim() != "svd" {
+            continue;
+        }
+
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.split_once('"'))
+            .map(|(value, _)| value)?;
+
+        return Some(value.to_string());
+    }
+
+    None
+}
+
+/// Remove all `<!-- ... -->` comments from the XML text.
+fn strip_comments(xml: &str) -> String {
+    let mut stripped = String::with_capacity(xml.len());
+    let mut rest = xml;
+
+    while let Some(start) = rest.find("<!--") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start..].find("-->") {
+            Some(end) => &rest[start + end + 3..],
+            None => "",
+        };
+    }
+
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Return the trimmed text of the first `<tag>` element in `xml`.
+fn element_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)? + start;
+
+    Some(xml[start..end].trim())
+}
+
+/// Collect all `<interrupt>` elements, sorted by IRQ number. The same
+/// interrupt may be listed under several peripherals, e.g. `TIM6_DAC`.
+fn parse_interrupts(svd: &str) -> Result<Vec<Irq>, String> {
+    let mut irqs: Vec<Irq> = Vec::new();
+    let mut rest = svd;
+
+    while let Some(start) = rest.find("<interrupt>") {
+        let body = &rest[start + "<interrupt>".len()..];
+        let end = body
+            .find("</interrupt>")
+            .ok_or("unterminated `<interrupt>` element")?;
+        rest = &body[end + "</interrupt>".len()..];
+        let body = &body[..end];
+
+        let name = element_text(body, "name")
+            .ok_or("`<interrupt>` element without `<name>`")?
+            .to_uppercase();
+        let value = element_text(body, "value")
+            .ok_or_else(|| format!("interrupt `{}` has no `<value>`", name))?;
+        let number = parse_number(value)
+            .ok_or_else(|| format!("interrupt `{}` has invalid value `{}`", name, value))?;
+
+        match irqs.iter().find(|irq| irq.name == name) {
+            Some(irq) if irq.number != number => {
+                return Err(format!(
+                    "interrupt `{}` is listed with both value {} and {}",
+                    name, irq.number, number
+                ))
+            }
+            Some(_) => {}
+            None => irqs.push(Irq {
+                name: Cow::Owned(name),
+                number,
+            }),
+        }
+    }
+
+    irqs.sort_by_key(|irq| irq.number);
+
+    Ok(irqs)
+}
+
+/// Parse a decimal or `0x` prefixed hexadecimal SVD scaled integer.
+fn parse_number(value: &str) -> Option<u16> {
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names_and_numbers(irqs: &[Irq]) -> Vec<(&str, u16)> {
+        irqs.iter()
+            .map(|irq| (irq.name.as_ref(), irq.number))
+            .collect()
+    }
+
+    #[test]
+    fn config_svd_entry_reads_top_level_key() {
+        let config = "# Chip description\nsvd = \"svd/STM32F407.svd\" # comment\n";
+        assert_eq!(
+            config_svd_entry(config).as_deref(),
+            Some("svd/STM32F407.svd")
+        );
+    }
+
+    #[test]
+    fn config_svd_entry_ignores_other_keys() {
+        let config = "svd_dir = \"svd\"\nname = \"board\"\n  svd   =   \"chip.svd\"\n";
+        assert_eq!(config_svd_entry(config).as_deref(), Some("chip.svd"));
+    }
+
+    #[test]
+    fn config_svd_entry_stops_at_table() {
+        let config = "name = \"board\"\n[chip]\nsvd = \"chip.svd\"\n";
+        assert_eq!(config_svd_entry(config), None);
+    }
+
+    #[test]
+    fn config_svd_entry_requires_quoted_string() {
+        assert_eq!(config_svd_entry("svd = chip.svd\n"), None);
+        assert_eq!(config_svd_entry("svd = \"chip.svd\n"), None);
+    }
+
+    #[test]
+    fn parse_number_accepts_decimal_and_hex() {
+        assert_eq!(parse_number("55"), Some(55));
+        assert_eq!(parse_number("0x37"), Some(55));
+        assert_eq!(parse_number("0X3F"), Some(63));
+    }
+
+    #[test]
+    fn parse_number_rejects_invalid() {
+        assert_eq!(parse_number(""), None);
+        assert_eq!(parse_number("0x"), None);
+        assert_eq!(parse_number("-1"), None);
+        assert_eq!(parse_number("70000"), None);
+        assert_eq!(parse_number("#101"), None);
+    }
+
+    #[test]
+    fn strip_comments_removes_all_comments() {
+        let xml = "<a><!-- one --><b>1</b><!-- <interrupt> --></a>";
+        assert_eq!(strip_comments(xml), "<a><b>1</b></a>");
+    }
+
+    #[test]
+    fn strip_comments_drops_unterminated_comment() {
+        assert_eq!(strip_comments("<a>1</a><!-- <b>2</b>"), "<a>1</a>");
+    }
+
+    #[test]
+    fn element_text_returns_first_trimmed_text() {
+        let xml = "<device><name> STM32F407 </name><name>GPIOA</name></device>";
+        assert_eq!(element_text(xml, "name"), Some("STM32F407"));
+        assert_eq!(element_text(xml, "value"), None);
+    }
+
+    #[test]
+    fn element_text_requires_closing_tag() {
+        assert_eq!(element_text("<name>STM32F407", "name"), None);
+    }
+
+    #[test]
+    fn parse_interrupts_sorts_and_merges_duplicates() {
+        let svd = "\
+            <peripheral><name>TIM7</name>\
+              <interrupt><name>TIM7</name><value>55</value></interrupt>\
+            </peripheral>\
+            <peripheral><name>TIM6</name>\
+              <interrupt><name>TIM6_DAC</name><value>0x36</value></interrupt>\
+            </peripheral>\
+            <peripheral><name>DAC</name>\
+              <interrupt><name>TIM6_DAC</name><value>54</value></interrupt>\
+            </peripheral>\
+            <peripheral><name>DMA1</name>\
+              <interrupt><name>DMA1_Stream0</name><value>11</value></interrupt>\
+            </peripheral>";
+        let irqs = parse_interrupts(svd).unwrap();
+        assert_eq!(
+            names_and_numbers(&irqs),
+            [("DMA1_STREAM0", 11), ("TIM6_DAC", 54), ("TIM7", 55)]
+        );
+    }
+
+    #[test]
+    fn parse_interrupts_ignores_commented_out_interrupts() {
+        let svd = strip_comments(
+            "<!-- <interrupt><name>OLD</name><value>1</value></interrupt> -->\
+             <interrupt><name>NEW</name><value>2</value></interrupt>",
+        );
+        let irqs = parse_interrupts(&svd).unwrap();
+        assert_eq!(names_and_numbers(&irqs), [("NEW", 2)]);
+    }
+
+    #[test]
+    fn parse_interrupts_rejects_conflicting_duplicates() {
+        let svd = "\
+            <interrupt><name>TIM7</name><value>55</value></interrupt>\
+            <interrupt><name>TIM7</name><value>56</value></interrupt>";
+        match parse_interrupts(svd) {
+            Err(error) => assert!(error.contains("TIM7")),
+            Ok(_) => panic!("conflicting values were accepted"),
+        }
+    }
+
+    #[test]
+    fn parse_interrupts_rejects_malformed_elements() {
+        let unterminated = "<interrupt><name>TIM7</name><value>55</value>";
+        assert!(parse_interrupts(unterminated).is_err());
+
+        let nameless = "<interrupt><value>55</value></interrupt>";
+        assert!(parse_interrupts(nameless).is_err());
+
+        let valueless = "<interrupt><name>TIM7</name></interrupt>";
+        assert!(parse_interrupts(valueless).is_err());
+
+        let invalid = "<interrupt><name>TIM7</name><value>TIM7</value></interrupt>";
+        assert!(parse_interrupts(invalid).is_err());
+    }
+}
@@ -0,0 +1,521 @@
+//! Implementation of the [`#[task]`](crate::task) attribute macro.
+
+use crate::{
+    args::{self, AttrArg, CratePaths},
+    error_with_item, trampoline, Errors, ForwardedAttrs,
+};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote_spanned, ToTokens};
+use std::ops::RangeInclusive;
+use syn::{
+    parse_quote_spanned,
+    spanned::Spanned,
+    visit_mut::{self, VisitMut},
+    Error, Expr, FnArg, Ident, ItemFn, Path, Result, ReturnType, Signature, Type,
+};
+
+macro_rules! task_macro_arg_error {
+    () => {
+        "Task's argument must be one of `priority = N`, `stack = N`, `autostart` \
+        or `crate = path`."
+    };
+}
+
+macro_rules! task_macro_retval_error {
+    () => {
+        "Task function's return type must be () or !."
+    };
+}
+
+/// Name of the link section holding the tasks spawned at boot.
+const STATIC_TASK_SECTION: &str = ".hopter_static_tasks";
+
+/// Number of task priority levels of Hopter. Lower values are more urgent,
+/// and the least urgent level is reserved for the idle task.
+const TASK_PRIORITY_LEVELS: u8 = 16;
+
+/// Smallest and largest accepted initial stack size of a task.
+pub(crate) const TASK_STACK_RANGE: RangeInclusive<usize> = 256..=(1 << 20);
+
+/// The parsed arguments of the task attribute.
+struct TaskArgs {
+    /// The priority of the task spawned at boot.
+    priority: Option<u8>,
+    /// The initial stack size in bytes of the task spawned at boot.
+    stack: Option<usize>,
+    /// The `autostart` option, if given.
+    autostart: Option<Path>,
+    /// Paths to the runtime crates used by the generated code.
+    crates: CratePaths,
+}
+
+pub(crate) fn expand(attr_args: &[AttrArg], task_func: ItemFn) -> TokenStream {
+    let mut errors = Errors::default();
+
+    if let Err(error) = check_task_function_signature(&task_func.sig) {
+        errors.combine(error);
+    }
+
+    let args = match parse_task_args(attr_args) {
+        Ok(args) => Some(args),
+        Err(error) => {
+            errors.combine(error);
+            None
+        }
+    };
+
+    // The kernel has no argument to pass to a task it spawns at boot.
+    if let Some(TaskArgs {
+        autostart: Some(_), ..
+    }) = &args
+    {
+        if let Some(input) = task_func.sig.inputs.first() {
+            errors.push(input, "Task with `autostart` cannot take an argument.");
+        }
+    }
+
+    if let Err(error) = errors.finish() {
+        return error_with_item(error, &task_func);
+    }
+
+    let args = args.unwrap();
+    let CratePaths { hopter, alloc, .. } = &args.crates;
+
+    // Store the task function's name.
+    let vis = &task_func.vis;
+    let func_name = &task_func.sig.ident;
+    let span = func_name.span();
+    let trampoline_name = format_ident!("__{}_trampoline", func_name, span = span);
+
+    let forwarded = ForwardedAttrs::new(&task_func);
+    let ForwardedAttrs {
+        cfg: cfg_attrs,
+        func: func_attrs,
+        ..
+    } = &forwarded;
+
+    // A task without argument is passed a null pointer, which is also what
+    // the kernel passes to the tasks it spawns at boot.
+    let arg_ty = match task_func.sig.inputs.first() {
+        Some(FnArg::Typed(arg)) => arg.ty.to_token_stream(),
+        _ => quote_spanned! {span=> () },
+    };
+
+    // The module generated for the task is nested within the module of the
+    // task function, where paths such as `super::Led` mean something else.
+    let nested_arg_ty = match task_func.sig.inputs.first() {
+        Some(FnArg::Typed(arg)) => nest_type(&arg.ty).to_token_stream(),
+        _ => arg_ty.clone(),
+    };
+    let (spawn_arg, arg_ptr, trampoline_body) = if task_func.sig.inputs.is_empty() {
+        (
+            None,
+            quote_spanned! {span=> ::core::ptr::null_mut() },
+            quote_spanned! {span=>
+                let _ = arg;
+                #func_name()
+            },
+        )
+    } else {
+        (
+            Some(quote_spanned! {span=> arg: #nested_arg_ty, }),
+            quote_spanned! {span=>
+                #alloc::boxed::Box::into_raw(#alloc::boxed::Box::new(arg)) as *mut u8
+            },
+            unboxing_call(
+                func_name,
+                &arg_ty,
+                Some(quote_spanned! {span=> *arg }),
+                alloc,
+            ),
+        )
+    };
+
+    // The task never runs if it fails to spawn, so the boxed argument is
+    // released by `spawn` instead.
+    let reclaim_arg = spawn_arg.as_ref().map(|_| {
+        quote_spanned! {span=>
+            if result.is_err() {
+                ::core::mem::drop(unsafe {
+                    #alloc::boxed::Box::from_raw(arg_ptr as *mut #nested_arg_ty)
+                });
+            }
+        }
+    });
+
+    // The argument crosses into another task, so it must be sendable. The
+    // assertion points at the argument type if it is not.
+    let send_assertion = spawn_arg.as_ref().map(|_| {
+        quote_spanned! {arg_ty.span()=>
+            const _: fn() = || {
+                fn assert_send<T: ::core::marker::Send + 'static>() {}
+                assert_send::<#nested_arg_ty>();
+            };
+        }
+    });
+
+    // Tasks are told apart by name, so each name is exported as a symbol,
+    // which is then duplicated when linking if the name is not unique.
+    let name = func_name.to_string();
+    let name_symbol = format!("__hopter_task_{}", name);
+
+    let name_export =
+        trampoline::unsafe_attr(span, quote_spanned! {span=> export_name = #name_symbol });
+    let static_task_section = trampoline::unsafe_attr(
+        span,
+        quote_spanned! {span=> link_section = #STATIC_TASK_SECTION },
+    );
+
+    let autostart = args.autostart.is_some();
+    let static_task = match (args.autostart, args.priority, args.stack) {
+        (Some(_), Some(priority), Some(stack)) => Some(quote_spanned! {span=>
+            /// The entry of the task in the table of tasks spawned at boot.
+            #[used]
+            #static_task_section
+            static STATIC_TASK: #hopter::task::StaticTask = #hopter::task::StaticTask {
+                name: #name,
+                entry: super::#trampoline_name,
+                priority: #priority,
+                stack_size: #stack,
+            };
+        }),
+        _ => None,
+    };
+
+    let module_doc = format!("Spawning of the [`{}`] task.", func_name);
+    let spawn_doc = format!(
+        "Spawn a task running [`{}`](super::{}) with the given priority and \
+        initial stack size in bytes.",
+        func_name, func_name
+    );
+
+    // Tasks spawned at boot are only ever spawned by the kernel.
+    let spawn = (!autostart).then(|| {
+        quote_spanned! {span=>
+            #[doc = #spawn_doc]
+            pub fn spawn(
+                #spawn_arg
+                priority: u8,
+                stack: usize,
+            ) -> ::core::result::Result<(), #hopter::task::TaskBuildError> {
+                let arg_ptr = #arg_ptr;
+                let arg = ::core::sync::atomic::AtomicPtr::new(arg_ptr);
+                let result = #hopter::task::build()
+                    .set_entry(move || super::#trampoline_name(arg))
+                    .set_priority(priority)
+                    .set_stack_init_size(stack)
+                    .spawn();
+                #reclaim_arg
+                result
+            }
+        }
+    });
+
+    quote_spanned! {span=>
+        #func_attrs
+        extern "C-unwind" fn #trampoline_name(arg: ::core::sync::atomic::AtomicPtr<u8>) {
+            #trampoline_body
+        }
+
+        #cfg_attrs
+        #[doc = #module_doc]
+        #[allow(unused_imports)]
+        #vis mod #func_name {
+            use super::*;
+
+            #send_assertion
+
+            #[used]
+            #name_export
+            static TASK_NAME: u8 = 0;
+
+            #static_task
+
+            #spawn
+        }
+
+        #task_func
+    }
+    .into()
+}
+
+/// Rewrite the paths in `ty` that are relative to the module of the task
+/// function, so that they resolve the same from the module generated within
+/// it. `self::Led` becomes `super::Led` and `super::Led` becomes
+/// `super::super::Led`. Other paths are found through `use super::*`.
+fn nest_type(ty: &Type) -> Type {
+    let mut ty = ty.clone();
+    NestedPaths.visit_type_mut(&mut ty);
+    ty
+}
+
+/// Rewrites the relative paths visited for [`nest_type`].
+struct NestedPaths;
+
+impl VisitMut for NestedPaths {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        if path.leading_colon.is_none() {
+            if let Some(first) = path.segments.first_mut() {
+                let span = first.ident.span();
+                if first.ident == "self" {
+                    first.ident = Ident::new("super", span);
+                } else if first.ident == "super" {
+                    path.segments.insert(0, parse_quote_spanned!(span=> super));
+                }
+            }
+        }
+
+        visit_mut::visit_path_mut(self, path);
+    }
+}
+
+/// Generate the body of a trampoline that receives a boxed `arg_ty` through
+/// an `AtomicPtr<u8>` named `arg`, unboxes it, and calls `func_name` with
+/// `call_arg`. Without `call_arg`, the box is only released.
+pub(crate) fn unboxing_call(
+    func_name: &Ident,
+    arg_ty: &TokenStream2,
+    call_arg: Option<TokenStream2>,
+    alloc: &TokenStream2,
+) -> TokenStream2 {
+    let span = func_name.span();
+
+    let release = match call_arg {
+        Some(_) => None,
+        None => Some(quote_spanned! {span=> ::core::mem::drop(arg); }),
+    };
+
+    quote_spanned! {span=>
+        let arg = arg.load(::core::sync::atomic::Ordering::SeqCst) as *mut #arg_ty;
+        let arg = unsafe { #alloc::boxed::Box::from_raw(arg) };
+        #release
+        #func_name(#call_arg)
+    }
+}
+
+/// The task attribute accepts the `autostart` option, which requires the
+/// `priority = N` and `stack = N` options, and the `crate = path` option.
+fn parse_task_args(attr_args: &[AttrArg]) -> Result<TaskArgs> {
+    let mut errors = Errors::default();
+    let mut priority = None;
+    let mut stack = None;
+    let mut autostart: Option<Path> = None;
+    let mut krate: Option<Path> = None;
+
+    for arg in attr_args {
+        match arg {
+            AttrArg::Path(path) if path.is_ident("autostart") => {
+                if autostart.is_some() {
+                    errors.push(path, "Duplicated `autostart` option.");
+                }
+                autostart = Some(path.clone());
+            }
+            AttrArg::NameValue { name, value, .. } if name == "priority" => {
+                if priority.is_some() {
+                    errors.push(name, "Duplicated `priority` option.");
+                }
+                // Remember the option even if invalid, to not report it as
+                // missing as well.
+                let value = parse_task_priority(value)
+                    .map_err(|error| errors.combine(error))
+                    .ok();
+                priority = Some((name, value));
+            }
+            AttrArg::NameValue { name, value, .. } if name == "stack" => {
+                if stack.is_some() {
+                    errors.push(name, "Duplicated `stack` option.");
+                }
+                let value = args::parse_size(value, "Task stack size", TASK_STACK_RANGE)
+                    .map_err(|error| errors.combine(error))
+                    .ok();
+                stack = Some((name, value));
+            }
+            AttrArg::NameValue { name, value, .. } if name == "crate" => {
+                if krate.is_some() {
+                    errors.push(name, "Duplicated `crate` option.");
+                }
+                match args::expr_to_path(value) {
+                    Ok(path) => krate = Some(path),
+                    Err(error) => errors.combine(error),
+                }
+            }
+            _ => errors.push(arg, task_macro_arg_error!()),
+        }
+    }
+
+    // The priority and the stack size of spawned tasks are passed to `spawn`.
+    match &autostart {
+        Some(autostart) => {
+            if priority.is_none() {
+                errors.push(autostart, "Task with `autostart` requires `priority = N`.");
+            }
+            if stack.is_none() {
+                errors.push(autostart, "Task with `autostart` requires `stack = N`.");
+            }
+        }
+        None => {
+            let names = priority
+                .iter()
+                .map(|(name, _)| name)
+                .chain(stack.iter().map(|(name, _)| name));
+            for name in names {
+                errors.push(
+                    name,
+                    format!(
+                        "`{}` only applies to tasks with `autostart`. Otherwise, it is \
+                        passed to `spawn`.",
+                        name
+                    ),
+                );
+            }
+        }
+    }
+
+    errors.finish().map(|_| TaskArgs {
+        priority: priority.and_then(|(_, value)| value),
+        stack: stack.and_then(|(_, value)| value),
+        autostart,
+        crates: CratePaths::new(krate.as_ref()),
+    })
+}
+
+/// Validate a task priority, which must be above the idle task's.
+pub(crate) fn parse_task_priority(value: &Expr) -> Result<u8> {
+    let priority = args::parse_int::<u8>(value, "Task priority")?;
+
+    if priority >= TASK_PRIORITY_LEVELS - 1 {
+        return Err(Error::new_spanned(
+            value,
+            format!(
+                "Task priority must be in the range 0..={}, as {} is reserved \
+                for the idle task.",
+                TASK_PRIORITY_LEVELS - 2,
+                TASK_PRIORITY_LEVELS - 1
+            ),
+        ));
+    }
+
+    Ok(priority)
+}
+
+/// A task function should satisfy the following signature requirements:
+/// - Has no argument, or one argument of a `Send + 'static` type.
+/// - Returns `()` or `!`.
+/// - Has the Rust ABI.
+/// - Is not generic.
+/// - Is not `async`.
+/// - Is not `unsafe`.
+/// - Is not variadic.
+fn check_task_function_signature(sig: &Signature) -> Result<()> {
+    let mut errors = Errors::default();
+
+    if let Some(FnArg::Receiver(receiver)) = sig.inputs.first() {
+        errors.push(receiver, "Task function cannot take `self`.");
+    }
+
+    for extra in sig.inputs.iter().skip(1) {
+        errors.push(
+            extra,
+            "Task function can take at most one argument. Several values can \
+            be passed as a tuple or a struct.",
+        );
+    }
+
+    match &sig.output {
+        // No return type specification.
+        ReturnType::Default => {}
+        // Specified return type as `-> ()` or `-> !`.
+        ReturnType::Type(_, b) => match &**b {
+            Type::Tuple(t) if t.elems.is_empty() => {}
+            Type::Never(_) => {}
+            _ => errors.push(b, task_macro_retval_error!()),
+        },
+    }
+
+    if let Some(abi) = &sig.abi {
+        errors.push(abi, "Task function must have the Rust ABI.");
+    }
+
+    if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+        errors.push(&sig.generics, "Task function cannot be generic.");
+    }
+
+    if let Some(asyncness) = &sig.asyncness {
+        errors.push(asyncness, "Task function cannot be `async`.");
+    }
+
+    if let Some(unsafety) = &sig.unsafety {
+        errors.push(unsafety, "Task function must be safe.");
+    }
+
+    if let Some(variadic) = &sig.variadic {
+        errors.push(variadic, "Task function cannot be variadic.");
+    }
+
+    errors.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn priority(value: &str) -> Result<u8> {
+        parse_task_priority(&syn::parse_str(value).unwrap())
+    }
+
+    #[test]
+    fn parse_task_priority_accepts_levels_above_idle() {
+        assert_eq!(priority("0").unwrap(), 0);
+        assert_eq!(priority("14").unwrap(), 14);
+    }
+
+    #[test]
+    fn parse_task_priority_rejects_idle_priority() {
+        let error = priority("15").unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Task priority must be in the range 0..=14, as 15 is reserved for the idle task."
+        );
+        assert!(priority("16").is_err());
+    }
+
+    #[test]
+    fn parse_task_priority_rejects_non_integers() {
+        assert!(priority("-1").is_err());
+        assert!(priority("256").is_err());
+        assert!(priority("3u8").is_err());
+    }
+
+    fn nested(ty: &str) -> String {
+        let ty: Type = syn::parse_str(ty).unwrap();
+        nest_type(&ty).to_token_stream().to_string()
+    }
+
+    #[test]
+    fn nest_type_rewrites_relative_paths() {
+        assert_eq!(nested("self::Led"), "super :: Led");
+        assert_eq!(nested("super::Led"), "super :: super :: Led");
+        assert_eq!(
+            nested("super::super::Led"),
+            "super :: super :: super :: Led"
+        );
+    }
+
+    #[test]
+    fn nest_type_keeps_other_paths() {
+        assert_eq!(nested("Led"), "Led");
+        assert_eq!(nested("crate::Led"), "crate :: Led");
+        assert_eq!(nested("::board::Led"), ":: board :: Led");
+        assert_eq!(nested("board::self_test::Led"), "board :: self_test :: Led");
+    }
+
+    #[test]
+    fn nest_type_rewrites_nested_paths() {
+        assert_eq!(
+            nested("(self::Led, &'static [super::Pin; 2])"),
+            "(super :: Led , & 'static [super :: super :: Pin ; 2])"
+        );
+        assert_eq!(nested("Box<self::Led>"), "Box < super :: Led >");
+    }
+}
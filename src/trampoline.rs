@@ -0,0 +1,143 @@
+//! Generation of the assembly entry stubs that vector table entries point to.
+//!
+//! Three backends exist, selected when this crate is built:
+//! - With the `global_asm` feature, or on a stable compiler older than Rust
+//!   1.88, the stub is defined in a `global_asm!` block and declared to Rust
+//!   with an `extern "C"` block. This works on every stable compiler.
+//! - Otherwise, on Rust 1.88 or later, the stub is a naked function marked
+//!   `#[unsafe(naked)]` with a `naked_asm!` body.
+//! - Otherwise, on an older nightly compiler, the stub is a `#[naked]`
+//!   function, which requires
+//!   `#![feature(naked_functions)]`. Its body is `naked_asm!` from Rust 1.84,
+//!   and `asm!` using `options(noreturn)` on older compilers.
+
+use crate::ForwardedAttrs;
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::quote_spanned;
+use syn::{parse_quote_spanned, token, AttrStyle, Attribute};
+
+/// Generate an entry stub exported as `symbol`, made of the given
+/// `instructions`. The instructions may refer to the named `operands`, such
+/// as `handler_func = sym my_handler`. The stub is known to Rust as
+/// `entry_name`, and generated code carries the span of `entry_name`. The
+/// `forwarded` attributes of the user function decide whether the stub is
+/// compiled and which section it is placed into.
+pub(crate) fn generate_entry(
+    symbol: &str,
+    entry_name: &Ident,
+    instructions: &[&str],
+    operands: TokenStream2,
+    forwarded: &ForwardedAttrs,
+) -> TokenStream2 {
+    let span = entry_name.span();
+    let ForwardedAttrs {
+        cfg: cfg_attrs,
+        func: func_attrs,
+        link_section,
+    } = forwarded;
+
+    if cfg!(feature = "global_asm") || cfg!(hopter_global_asm) {
+        let section = match link_section {
+            Some(section) => section.clone(),
+            None => format!(".text.{}", symbol),
+        };
+        let prologue = [
+            format!(".pushsection {},\"ax\",%progbits", section),
+            format!(".globl {}", symbol),
+            format!(".type {},%function", symbol),
+            ".thumb_func".to_string(),
+            format!("{}:", symbol),
+        ];
+        let epilogue = [
+            ".ltorg".to_string(),
+            format!(".size {0}, . - {0}", symbol),
+            ".popsection".to_string(),
+        ];
+
+        let extern_block = extern_block(span);
+
+        quote_spanned! {span=>
+            #cfg_attrs
+            ::core::arch::global_asm!(
+                #(#prologue,)*
+                #(#instructions,)*
+                #(#epilogue,)*
+                #operands
+            );
+
+            #cfg_attrs
+            #extern_block {
+                #[link_name = #symbol]
+                #[allow(dead_code)]
+                fn #entry_name();
+            }
+        }
+    } else if cfg!(hopter_unsafe_naked) {
+        let export_name = unsafe_attr(span, quote_spanned! {span=> export_name = #symbol });
+        quote_spanned! {span=>
+            #func_attrs
+            #[unsafe(naked)]
+            #export_name
+            unsafe extern "C" fn #entry_name() {
+                ::core::arch::naked_asm!(
+                    #(#instructions,)*
+                    #operands
+                )
+            }
+        }
+    } else if cfg!(hopter_naked_asm) {
+        let export_name = unsafe_attr(span, quote_spanned! {span=> export_name = #symbol });
+        quote_spanned! {span=>
+            #func_attrs
+            #[naked]
+            #export_name
+            unsafe extern "C" fn #entry_name() {
+                ::core::arch::naked_asm!(
+                    #(#instructions,)*
+                    #operands
+                )
+            }
+        }
+    } else {
+        let export_name = unsafe_attr(span, quote_spanned! {span=> export_name = #symbol });
+        quote_spanned! {span=>
+            #func_attrs
+            #[naked]
+            #export_name
+            unsafe extern "C" fn #entry_name() {
+                ::core::arch::asm!(
+                    #(#instructions,)*
+                    #operands
+                    options(noreturn)
+                )
+            }
+        }
+    }
+}
+
+/// The keywords opening a block of foreign declarations, which must be
+/// `unsafe extern "C"` in the 2024 edition but is only accepted from Rust 1.82.
+pub(crate) fn extern_block(span: Span) -> TokenStream2 {
+    if cfg!(hopter_unsafe_attr_extern) {
+        quote_spanned! {span=> unsafe extern "C" }
+    } else {
+        quote_spanned! {span=> extern "C" }
+    }
+}
+
+/// The attribute `#[meta]`, for `no_mangle`, `export_name` and
+/// `link_section`. It must be written `#[unsafe(meta)]` in the 2024 edition,
+/// which is only accepted from Rust 1.82.
+pub(crate) fn unsafe_attr(span: Span, meta: TokenStream2) -> Attribute {
+    if cfg!(hopter_unsafe_attr_extern) {
+        Attribute {
+            pound_token: token::Pound(span),
+            style: AttrStyle::Outer,
+            bracket_token: token::Bracket(span),
+            path: Ident::new("unsafe", span).into(),
+            tokens: quote_spanned! {span=> (#meta) },
+        }
+    } else {
+        parse_quote_spanned! {span=> #[#meta] }
+    }
+}